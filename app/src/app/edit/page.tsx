@@ -9,18 +9,18 @@ import { toast } from "sonner";
 import { useFlashToast } from "@/context/toast";
 import { LoadingScreen } from "@/components/LoadingIndicator";
 import { useFetchWithDelay } from "@/hooks/fetch";
+import { pageApiPath, pageNameFromPath } from "@/lib/helpers";
 import { ErrorScreen } from "@/components/ErrorScreen";
 
 export default function EditPage() {
   const { flashToastAndNavigate } = useFlashToast();
   const pathname = usePathname();
-  const segments = pathname.split("/").filter(Boolean);
   const router = useRouter();
-  const pageName = segments[1];
+  const pageName = pageNameFromPath(pathname);
 
   const state = useFetchWithDelay(
     () =>
-      fetch(`/api/page/${pageName}`, {
+      fetch(pageApiPath(pageName), {
         headers: { Accept: "application/json" },
       }).then((res) => {
         if (!res.ok) {
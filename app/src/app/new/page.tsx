@@ -4,11 +4,11 @@ import NoSsr from "@/components/NoSsr";
 import { PageForm } from "@/components/PageForm";
 import { usePathname } from "next/navigation";
 import { useFlashToast } from "@/context/toast";
+import { pageNameFromPath } from "@/lib/helpers";
 
 export default function NewPage() {
   const pathname = usePathname();
-  const segments = pathname.split("/").filter(Boolean);
-  const page = { pageName: segments[1] || "", pageContent: "", pageToc: "", updatedAt: "", pageFiles: [] };
+  const page = { pageName: pageNameFromPath(pathname), pageContent: "", pageToc: "", updatedAt: "", pageFiles: [] };
   const { flashToastAndNavigate } = useFlashToast();
 
   const onSuccess = (pageName: string) => {
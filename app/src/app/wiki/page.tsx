@@ -5,7 +5,7 @@ import { Separator } from "@/components/ui/separator";
 import { Button } from "@/components/ui/button";
 import { LoadingScreen } from "@/components/LoadingIndicator";
 import type { Page, PagePreview } from "@/lib/model";
-import { formatUtcDateTime } from "@/lib/helpers";
+import { formatUtcDateTime, pageApiPath, pageNameFromPath } from "@/lib/helpers";
 import Link from "next/link";
 import { useFetchWithDelay } from "@/hooks/fetch";
 import { Edit } from "lucide-react";
@@ -21,7 +21,7 @@ export default function WikiRouter() {
       {segments.length === 1 ? (
         <WikiIndex />
       ) : (
-        <WikiPage pageName={segments[1]} />
+        <WikiPage pageName={pageNameFromPath(pathname)} />
       )}
     </NoSsr>
   );
@@ -78,7 +78,7 @@ function WikiPage({ pageName }: { pageName: string }) {
 
   const state = useFetchWithDelay(
     () =>
-      fetch(`/api/page/${pageName}?include_files=true`, {
+      fetch(`${pageApiPath(pageName)}?include_files=true`, {
         headers: { Accept: "application/json" },
       }).then((res) => {
         if (!res.ok) {
@@ -119,6 +119,18 @@ function WikiPage({ pageName }: { pageName: string }) {
 
     return (
         <div className="flex flex-col px-4">
+          {page.breadcrumbs && page.breadcrumbs.length > 0 && (
+            <nav className="mt-4 text-sm text-muted-foreground">
+              {page.breadcrumbs.map((crumb) => (
+                <span key={crumb.pageName}>
+                  <Link href={`/wiki/${crumb.pageName}`}>
+                    {crumb.pageName.split("/").pop()}
+                  </Link>
+                  {" / "}
+                </span>
+              ))}
+            </nav>
+          )}
           <div className="flex justify-between space-x-4 items-center">
             <h3 className="mt-8 mb-4 text-4xl font-semibold tracking-tight">
               {pageName}
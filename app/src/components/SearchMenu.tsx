@@ -14,11 +14,12 @@ import {
 } from "@/components/ui/command";
 import Link from "next/link";
 import { useSearch } from "@/context/search";
-import type { SearchResult } from "@/lib/model";
+import type { SearchResponse, SearchResult } from "@/lib/model";
 
 export function SearchMenu() {
   const [query, setQuery] = useState<string>("");
   const [results, setResults] = useState<SearchResult[]>([]);
+  const [total, setTotal] = useState<number>(0);
   const { open, setOpen } = useSearch();
 
   useEffect(() => {
@@ -26,14 +27,15 @@ export function SearchMenu() {
 
     const fetchData = async () => {
       try {
-        const res = await fetch(`/api/search?q=${query}`, {
+        const res = await fetch(`/api/search?q=${encodeURIComponent(query)}`, {
           headers: { Accept: "application/json" },
         });
         if (!res.ok) {
           throw new Error(`HTTP request failed with status code ${res.status}`);
         }
-        const results = await res.json();
-        setResults(results);
+        const response: SearchResponse = await res.json();
+        setResults(response.results);
+        setTotal(response.total);
       } catch (err: any) {
         // setError(err.message);
       } finally {
@@ -55,12 +57,13 @@ export function SearchMenu() {
         <CommandList>
           <CommandEmpty>No results found.</CommandEmpty>
           {results.length > 0 && (
-            <CommandGroup heading={`Search Results (${results.length})`}>
+            <CommandGroup heading={`Search Results (${total})`}>
               {results.map((res) => {
                 return (
-                  <CommandItem key={res.rowId}>
-                    <span>{res.title}</span>
-                    <span>{res.body}</span>
+                  <CommandItem key={`${res.rowId}-${res.fileUuid ?? ""}`}>
+                    <span dangerouslySetInnerHTML={{ __html: res.titleHtml }} />
+                    {res.fileName && <span>{res.fileName}</span>}
+                    <span dangerouslySetInnerHTML={{ __html: res.snippetHtml }} />
                   </CommandItem>
                 );
               })}
@@ -5,6 +5,17 @@ export function formatUtcDateTime(dateTime: string, fmt?: string): string {
   return format(date, fmt ?? "EEE LLL d hh:mm aa");
 }
 
+// page names may contain slashes, so the name is everything after the
+// first path segment
+export function pageNameFromPath(pathname: string): string {
+  const segments = pathname.split("/").filter(Boolean);
+  return decodeURIComponent(segments.slice(1).join("/"));
+}
+
+export function pageApiPath(pageName: string): string {
+  return `/api/page/${encodeURIComponent(pageName)}`;
+}
+
 export function scrollToTop() {
   const topElement = document.getElementById("top");
   if (topElement) {
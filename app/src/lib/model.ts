@@ -5,6 +5,37 @@ export type Page = {
   pageToc: string;
   updatedAt: string;
   pageFiles: PageFile[];
+  pageTags?: string[];
+  pageProperties?: Record<string, string>;
+  breadcrumbs?: Breadcrumb[];
+  createdBy?: PageAuthor | null;
+  updatedBy?: PageAuthor | null;
+};
+
+export type Breadcrumb = {
+  pageName: string;
+  pageUuid: string | null;
+};
+
+export type PageChild = {
+  pageName: string;
+  pageUuid: string | null;
+  updatedAt: string | null;
+  descendantCount: number;
+};
+
+export type PageListing = {
+  pageUuid: string;
+  pageName: string;
+  createdAt: string;
+  updatedAt: string;
+  pageProperties: Record<string, string>;
+  previewText?: string;
+};
+
+export type PageList = {
+  pages: PageListing[];
+  nextCursor: string | null;
 };
 
 export type PagePreview = {
@@ -12,6 +43,13 @@ export type PagePreview = {
   pageName: string;
   previewText: string;
   updatedAt: string;
+  updatedBy?: PageAuthor | null;
+};
+
+export type PageAuthor = {
+  userUuid: string;
+  userLogin: string;
+  avatarUrl?: string | null;
 };
 
 export type PageFile = {
@@ -25,5 +63,47 @@ export type PageFile = {
 export type SearchResult = {
   rowId: number;
   title: string;
-  body: string;
+  titleHtml: string;
+  snippetHtml: string;
+  fileUuid?: string;
+  fileName?: string;
+};
+
+export type Tag = {
+  tagName: string;
+  pageCount: number;
+};
+
+export type Facet = {
+  value: string;
+  count: number;
+};
+
+export type SearchFacets = {
+  tags: Facet[];
+  editors: Facet[];
+  months: Facet[];
+};
+
+export type SearchResponse = {
+  results: SearchResult[];
+  total: number;
+  next?: string | null;
+  facets: SearchFacets;
+};
+
+export type Watch = {
+  pageName: string;
+  includeSubpages: boolean;
+  createdAt: string;
+};
+
+export type Change = {
+  changeId: number;
+  changedAt: string;
+  action: string;
+  detail: string | null;
+  userLogin: string | null;
+  pageUuid: string;
+  pageName: string;
 };
@@ -0,0 +1,11 @@
+-- Search queries saved by users under a name.
+CREATE TABLE IF NOT EXISTS saved_searches
+(
+    search_id             INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    search_uuid           TEXT                UNIQUE NOT NULL,
+    search_name           TEXT                NOT NULL,
+    search_query          TEXT                NOT NULL,
+    user_id               INTEGER             NOT NULL,
+    UNIQUE (user_id, search_name)
+);
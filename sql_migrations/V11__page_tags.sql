@@ -0,0 +1,10 @@
+-- Tags of pages, stored normalized.
+CREATE TABLE IF NOT EXISTS page_tags
+(
+    page_id               INTEGER             NOT NULL,
+    tag_name              TEXT                NOT NULL,
+    PRIMARY KEY (page_id, tag_name)
+);
+
+CREATE INDEX IF NOT EXISTS page_tags_tag_name
+  ON page_tags (tag_name);
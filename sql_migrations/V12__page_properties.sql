@@ -0,0 +1,11 @@
+-- Key/value properties of pages.
+CREATE TABLE IF NOT EXISTS page_properties
+(
+    page_id               INTEGER             NOT NULL,
+    prop_key              TEXT                NOT NULL,
+    prop_value            TEXT                NOT NULL,
+    PRIMARY KEY (page_id, prop_key)
+);
+
+CREATE INDEX IF NOT EXISTS page_properties_key_value
+  ON page_properties (prop_key, prop_value);
@@ -0,0 +1,10 @@
+-- Pages and namespaces users follow changes to, by name.
+CREATE TABLE IF NOT EXISTS watches
+(
+    watch_id              INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    user_id               INTEGER             NOT NULL,
+    page_name             TEXT                NOT NULL,
+    include_subpages      INTEGER             NOT NULL DEFAULT 0,
+    UNIQUE (user_id, page_name)
+);
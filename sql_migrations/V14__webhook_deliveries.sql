@@ -0,0 +1,18 @@
+-- Webhook payloads and the outcome of their latest delivery attempt.
+CREATE TABLE IF NOT EXISTS webhook_deliveries
+(
+    delivery_id           INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    delivery_uuid         TEXT                UNIQUE NOT NULL,
+    webhook_url           TEXT                NOT NULL,
+    event_name            TEXT                NOT NULL,
+    payload               TEXT                NOT NULL,
+    attempt_count         INTEGER             NOT NULL DEFAULT 0,
+    attempted_at          DATETIME            NULL,
+    status_code           INTEGER             NULL,
+    last_error            TEXT                NULL,
+    delivered_at          DATETIME            NULL
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_created_at
+  ON webhook_deliveries (created_at);
@@ -0,0 +1,28 @@
+-- Page name lookups for suggestions: a case-insensitive index for
+-- prefixes and a trigram index for substrings and similar names,
+-- independent of the tokenizer configured for `pages_fts`.
+CREATE INDEX IF NOT EXISTS page_name_nocase
+  ON pages (page_name COLLATE NOCASE);
+
+CREATE VIRTUAL TABLE page_names_fts USING fts5(
+    page_name,
+    content='pages',
+    content_rowid='page_id',
+    tokenize = 'trigram'
+);
+CREATE TRIGGER page_names_ai AFTER INSERT ON pages BEGIN
+  INSERT INTO page_names_fts(rowid, page_name)
+  VALUES (new.page_id, new.page_name);
+END;
+CREATE TRIGGER page_names_ad AFTER DELETE ON pages BEGIN
+  INSERT INTO page_names_fts(page_names_fts, rowid, page_name)
+  VALUES('delete', old.page_id, old.page_name);
+END;
+CREATE TRIGGER page_names_au AFTER UPDATE OF page_name ON pages BEGIN
+  INSERT INTO page_names_fts(page_names_fts, rowid, page_name)
+  VALUES('delete', old.page_id, old.page_name);
+  INSERT INTO page_names_fts(rowid, page_name)
+  VALUES (new.page_id, new.page_name);
+END;
+
+INSERT INTO page_names_fts(page_names_fts) VALUES('rebuild');
@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS users
+(
+    user_id               INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    updated_at            DATETIME            NOT NULL,
+    user_uuid             TEXT                UNIQUE NOT NULL,
+    github_id             INTEGER             UNIQUE NOT NULL,
+    user_login            TEXT                NOT NULL,
+    user_name             TEXT                NULL,
+    avatar_url            TEXT                NULL
+);
+
+CREATE INDEX IF NOT EXISTS user_uuid
+  ON users (user_uuid);
+CREATE INDEX IF NOT EXISTS user_login
+  ON users (user_login);
+
+CREATE TABLE IF NOT EXISTS sessions
+(
+    session_id            INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    expires_at            DATETIME            NOT NULL,
+    session_hash          TEXT                UNIQUE NOT NULL,
+    user_id               INTEGER             NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS session_hash
+  ON sessions (session_hash);
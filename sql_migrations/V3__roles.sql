@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN user_role TEXT NOT NULL DEFAULT 'editor';
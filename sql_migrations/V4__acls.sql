@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS user_groups
+(
+    group_id              INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    group_uuid            TEXT                UNIQUE NOT NULL,
+    group_name            TEXT                UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_group_members
+(
+    group_id              INTEGER             NOT NULL,
+    user_id               INTEGER             NOT NULL,
+    PRIMARY KEY (group_id, user_id)
+);
+
+-- An entry targets either a single page or every page whose
+-- name starts with a prefix, and grants a user or a group access.
+CREATE TABLE IF NOT EXISTS page_acls
+(
+    acl_id                INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    acl_uuid              TEXT                UNIQUE NOT NULL,
+    page_id               INTEGER             NULL,
+    name_prefix           TEXT                NULL,
+    user_id               INTEGER             NULL,
+    group_id              INTEGER             NULL,
+    acl_permission        TEXT                NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS page_acls_page_id
+  ON page_acls (page_id);
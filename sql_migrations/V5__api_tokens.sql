@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS api_tokens
+(
+    token_id              INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    last_used_at          DATETIME            NULL,
+    token_uuid            TEXT                UNIQUE NOT NULL,
+    token_name            TEXT                NOT NULL,
+    token_hash            TEXT                UNIQUE NOT NULL,
+    token_scope           TEXT                NOT NULL,
+    user_id               INTEGER             NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS token_hash
+  ON api_tokens (token_hash);
+CREATE INDEX IF NOT EXISTS api_tokens_user_id
+  ON api_tokens (user_id);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    audit_id              INTEGER             PRIMARY KEY NOT NULL,
+    created_at            DATETIME            NOT NULL,
+    user_id               INTEGER             NULL,
+    user_login            TEXT                NULL,
+    client_ip             TEXT                NULL,
+    audit_action          TEXT                NOT NULL,
+    audit_target          TEXT                NOT NULL,
+    audit_detail          TEXT                NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at
+  ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS audit_log_target
+  ON audit_log (audit_target);
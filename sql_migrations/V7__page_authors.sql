@@ -0,0 +1,12 @@
+ALTER TABLE pages ADD COLUMN created_by INTEGER NULL;
+ALTER TABLE pages ADD COLUMN updated_by INTEGER NULL;
+
+CREATE TABLE IF NOT EXISTS page_contributors
+(
+    page_id               INTEGER             NOT NULL,
+    user_id               INTEGER             NOT NULL,
+    first_edit_at         DATETIME            NOT NULL,
+    last_edit_at          DATETIME            NOT NULL,
+    edit_count            INTEGER             NOT NULL DEFAULT 0,
+    PRIMARY KEY (page_id, user_id)
+);
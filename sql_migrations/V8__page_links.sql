@@ -0,0 +1,10 @@
+-- Links between pages by name, the target page may not exist yet.
+CREATE TABLE IF NOT EXISTS page_links
+(
+    page_id               INTEGER             NOT NULL,
+    target_name           TEXT                NOT NULL,
+    PRIMARY KEY (page_id, target_name)
+);
+
+CREATE INDEX IF NOT EXISTS page_links_target_name
+  ON page_links (target_name);
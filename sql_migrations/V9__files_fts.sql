@@ -0,0 +1,31 @@
+ALTER TABLE files ADD COLUMN file_text TEXT NULL;
+
+-- Existing plain text attachments can be indexed as they are.
+UPDATE files SET file_text = CAST(file_content AS TEXT)
+  WHERE content_type LIKE 'text/plain%'
+     OR content_type LIKE 'text/markdown%'
+     OR content_type LIKE 'text/csv%';
+
+CREATE VIRTUAL TABLE files_fts USING fts5(
+    file_name,
+    file_text,
+    content='files',
+    content_rowid='file_id',
+    tokenize = 'trigram'
+);
+CREATE TRIGGER files_ai AFTER INSERT ON files BEGIN
+  INSERT INTO files_fts(rowid, file_name, file_text)
+  VALUES (new.file_id, new.file_name, new.file_text);
+END;
+CREATE TRIGGER files_ad AFTER DELETE ON files BEGIN
+  INSERT INTO files_fts(files_fts, rowid, file_name, file_text)
+  VALUES('delete', old.file_id, old.file_name, old.file_text);
+END;
+CREATE TRIGGER files_au AFTER UPDATE OF file_name, file_text ON files BEGIN
+  INSERT INTO files_fts(files_fts, rowid, file_name, file_text)
+  VALUES('delete', old.file_id, old.file_name, old.file_text);
+  INSERT INTO files_fts(rowid, file_name, file_text)
+  VALUES (new.file_id, new.file_name, new.file_text);
+END;
+
+INSERT INTO files_fts(files_fts) VALUES('rebuild');
@@ -0,0 +1,350 @@
+//! Authentication and access policy.
+use crate::{
+    config::AuthPolicy,
+    entity::{
+        acl::Access,
+        session::{SESSION_LIFETIME, SessionEntity},
+        token::{ApiTokenEntity, TokenScope},
+        user::{Role, UserEntity},
+    },
+    error::ServerError,
+    server::ServerState,
+};
+use axum::{
+    Extension,
+    extract::{ConnectInfo, Request},
+    http::{Method, header},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+/// Name of the session cookie.
+pub const SESSION_COOKIE: &str = "twilite_session";
+
+/// Caller making a request.
+#[derive(Debug, Clone, Default)]
+pub struct Identity {
+    pub user: Option<UserEntity>,
+    /// Effective role, `None` when the caller may not read.
+    pub role: Option<Role>,
+    /// Scope when authenticated with an API token.
+    pub scope: Option<TokenScope>,
+    /// Address of the remote peer.
+    pub client_ip: Option<IpAddr>,
+}
+
+impl Identity {
+    fn new(user: Option<UserEntity>, scope: Option<TokenScope>, policy: AuthPolicy) -> Self {
+        let role = match (&user, policy) {
+            (Some(user), _) => Some(user.user_role),
+            (None, AuthPolicy::Open) => Some(Role::Editor),
+            (None, AuthPolicy::LoginToEdit) => Some(Role::Reader),
+            (None, AuthPolicy::LoginToRead) => None,
+        };
+        let role = match scope {
+            Some(scope) => role.map(|role| role.min(scope.role())),
+            None => role,
+        };
+        Self {
+            user,
+            role,
+            scope,
+            client_ip: None,
+        }
+    }
+
+    /// Principal for page access entries, only signed in admins
+    /// bypass the entries.
+    pub fn access(&self) -> Access {
+        Access {
+            user_id: self.user.as_ref().map(|user| user.user_id),
+            admin: self.user.is_some() && self.role == Some(Role::Admin),
+        }
+    }
+
+    /// Require a signed in user.
+    pub fn require_user(&self) -> Result<&UserEntity, ServerError> {
+        self.user.as_ref().ok_or(ServerError::Unauthorized)
+    }
+
+    /// Require a user signed in with a session rather than an API token.
+    pub fn require_session(&self) -> Result<&UserEntity, ServerError> {
+        if self.scope.is_some() {
+            return Err(ServerError::Forbidden);
+        }
+        self.require_user()
+    }
+
+    /// Require at least the given role.
+    pub fn require(&self, role: Role) -> Result<(), ServerError> {
+        match self.role {
+            Some(current) if current >= role => Ok(()),
+            _ if self.user.is_none() => Err(ServerError::Unauthorized),
+            _ => Err(ServerError::Forbidden),
+        }
+    }
+}
+
+/// Create the session cookie for a session token.
+pub fn session_cookie(token: String, secure: bool) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(secure)
+        .max_age(SESSION_LIFETIME)
+        .build()
+}
+
+/// Routes that must be reachable to sign in.
+fn is_public(path: &str) -> bool {
+    path.starts_with("/login/") || path == "/logout" || path == "/api/github/callback"
+}
+
+/// Routes that render wiki documents.
+fn is_document(path: &str) -> bool {
+    path == "/"
+        || path == "/index.html"
+        || path.starts_with("/wiki/")
+        || path.starts_with("/edit/")
+        || path.starts_with("/new/")
+}
+
+pub(crate) fn is_write(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Extract the token from an `Authorization: Bearer` header.
+pub fn bearer_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim())
+}
+
+/// Resolve the caller from an API token or the session cookie
+/// and apply the auth policy.
+///
+/// An invalid API token is always rejected rather than treated
+/// as an anonymous request.
+pub async fn authenticate(
+    Extension(state): Extension<Arc<ServerState>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let jar = CookieJar::from_headers(request.headers());
+    let (user, scope) = if let Some(token) = bearer_token(&request) {
+        let pool = &state.reader;
+        let Some(owner) = ApiTokenEntity::find_user(pool, token).await? else {
+            return Err(ServerError::Unauthorized);
+        };
+        if owner.stale {
+            // recording the use should not hold up the request
+            let writer = state.writer.clone();
+            tokio::spawn(async move {
+                if let Err(e) = ApiTokenEntity::touch(&writer, owner.token_id).await {
+                    tracing::warn!(error = ?e, "auth::touch");
+                }
+            });
+        }
+        (Some(owner.user), Some(owner.token_scope))
+    } else if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        let pool = &state.reader;
+        (SessionEntity::find_user(pool, cookie.value()).await?, None)
+    } else {
+        (None, None)
+    };
+
+    let path = request.uri().path();
+    if user.is_none() && !is_public(path) {
+        match state.config.auth.policy {
+            AuthPolicy::Open => {}
+            AuthPolicy::LoginToEdit => {
+                if is_write(request.method()) {
+                    return Err(ServerError::Unauthorized);
+                }
+            }
+            AuthPolicy::LoginToRead => {
+                if is_document(path) {
+                    return Ok(Redirect::temporary("/login/github").into_response());
+                }
+                if is_write(request.method())
+                    || path.starts_with("/api/")
+                    || path.starts_with("/files/")
+                {
+                    return Err(ServerError::Unauthorized);
+                }
+            }
+        }
+    }
+
+    let mut identity = Identity::new(user, scope, state.config.auth.policy);
+    identity.client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    request.extensions_mut().insert(identity);
+    Ok(next.run(request).await)
+}
+
+pub async fn logout(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    jar: CookieJar,
+) -> Result<Response, ServerError> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        let pool = &state.writer;
+        SessionEntity::delete(pool, &identity, cookie.value()).await?;
+    }
+    let jar = jar.remove(Cookie::build(SESSION_COOKIE).path("/"));
+    Ok((jar, Redirect::temporary("/")).into_response())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Identity, authenticate};
+    use crate::{
+        config::AuthPolicy,
+        entity::{
+            audit::Actor,
+            token::{ApiTokenEntity, TokenScope},
+            user::{Role, test_user},
+        },
+        migrations::{migrate_connection, test_pool},
+        server::{ServerState, test_state},
+    };
+    use async_sqlite::rusqlite::Connection;
+    use axum::{
+        Extension, Router,
+        body::Body,
+        http::{Request, StatusCode, header},
+        middleware,
+        routing::get,
+    };
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// Names of the pages readable by an identity, `Oncall` is restricted
+    /// to the user with id 1 for itself and the pages below it.
+    fn readable(identity: &Identity) -> Vec<String> {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate_connection(&mut conn).unwrap();
+        for (page_id, page_name) in ["Oncall", "Oncall/Runbook", "Oncallers", "Public"]
+            .into_iter()
+            .enumerate()
+        {
+            conn.execute(
+                "INSERT INTO pages (page_id, created_at, updated_at, page_uuid, page_name) \
+                VALUES (?1, '', '', ?1, ?2)",
+                (page_id, page_name),
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO page_acls (created_at, acl_uuid, name_prefix, user_id, acl_permission) \
+            VALUES ('', 'acl', 'Oncall', 1, 'read')",
+            (),
+        )
+        .unwrap();
+
+        let condition = identity
+            .access()
+            .read_condition("p")
+            .unwrap_or("1".to_owned());
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT page_name FROM pages p WHERE {condition} ORDER BY page_name"
+            ))
+            .unwrap();
+        stmt.query_map((), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn anonymous_access() {
+        let open = Identity::new(None, None, AuthPolicy::Open);
+        assert_eq!(open.role, Some(Role::Editor));
+        assert_eq!(readable(&open), ["Oncallers", "Public"]);
+
+        let login_to_edit = Identity::new(None, None, AuthPolicy::LoginToEdit);
+        assert_eq!(login_to_edit.role, Some(Role::Reader));
+        assert_eq!(readable(&login_to_edit), ["Oncallers", "Public"]);
+
+        let login_to_read = Identity::new(None, None, AuthPolicy::LoginToRead);
+        assert_eq!(login_to_read.role, None);
+        assert!(!login_to_read.access().admin);
+    }
+
+    /// Status and role seen by a handler for a request with an optional
+    /// API token.
+    async fn role(state: &Arc<ServerState>, token: Option<&str>) -> (StatusCode, String) {
+        let app = Router::new()
+            .route(
+                "/api/role",
+                get(|Extension(identity): Extension<Identity>| async move {
+                    format!("{:?}", identity.role)
+                }),
+            )
+            .layer(middleware::from_fn(authenticate))
+            .layer(Extension(state.clone()));
+        let mut request = Request::builder().uri("/api/role");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn token_access() {
+        let pool = test_pool().await;
+        let state = test_state(pool.clone());
+        // the first user is an admin
+        let user = test_user(&pool, 1, "octocat").await;
+        assert_eq!(user.user_role, Role::Admin);
+        let created = ApiTokenEntity::create(
+            &pool,
+            Actor::default(),
+            &user,
+            "ci".to_owned(),
+            TokenScope::Read,
+        )
+        .await
+        .unwrap();
+        let created = serde_json::to_value(created).unwrap();
+        let token = created["token"].as_str().unwrap().to_owned();
+        let token_uuid = created["tokenUuid"].as_str().unwrap().parse().unwrap();
+
+        // the scope caps the role of the owner
+        let read = (StatusCode::OK, "Some(Reader)".to_owned());
+        assert_eq!(role(&state, Some(&token)).await, read);
+        let anonymous = (StatusCode::OK, "Some(Editor)".to_owned());
+        assert_eq!(role(&state, None).await, anonymous);
+
+        // unknown and revoked tokens are not treated as anonymous
+        assert_eq!(
+            role(&state, Some("twl_unknown")).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        ApiTokenEntity::delete(&pool, Actor::default(), &user, token_uuid)
+            .await
+            .unwrap();
+        assert_eq!(role(&state, Some(&token)).await.0, StatusCode::UNAUTHORIZED);
+    }
+}
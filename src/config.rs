@@ -1,4 +1,5 @@
-use clap::Parser;
+use crate::{entity::user::Role, rate_limit::LimitClass};
+use clap::{Parser, Subcommand};
 use serde::Deserialize;
 use std::{net::SocketAddr, path::PathBuf};
 
@@ -12,34 +13,202 @@ pub struct WikiLiteCli {
     /// Open on startup.
     #[clap(short, long)]
     pub open: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Rebuild the full-text search index with the configured
+    /// tokenizer, check its integrity and optimize it.
+    Reindex,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub bind: SocketAddr,
+    /// Address the wiki is reached at, such as
+    /// `https://wiki.example.com`, for absolute links in feeds.
+    /// Links use the `Host` header of the request when not set.
+    pub public_url: Option<String>,
     pub env: Option<PathBuf>,
     pub database: Database,
+    #[serde(default)]
+    pub auth: Auth,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    #[serde(default)]
+    pub search: Search,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             bind: "0.0.0.0:8776".parse().unwrap(),
+            public_url: None,
             env: None,
             database: Database::default(),
+            auth: Auth::default(),
+            rate_limit: RateLimit::default(),
+            search: Search::default(),
+            webhooks: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Database {
     pub path: String,
+    /// Connections for requests that only read.
+    #[serde(default = "default_read_connections")]
+    pub read_connections: usize,
+}
+
+fn default_read_connections() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
 }
 
 impl Default for Database {
     fn default() -> Self {
         Self {
             path: String::from("data/twilite.sqlite3"),
+            read_connections: default_read_connections(),
+        }
+    }
+}
+
+/// Who may read and edit the wiki.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthPolicy {
+    /// Anyone can read and edit, suitable for a private instance.
+    /// Deleting pages and the admin routes still need a signed in admin.
+    #[default]
+    Open,
+    /// Anyone can read, signed in users can edit.
+    LoginToEdit,
+    /// Only signed in users can read or edit.
+    LoginToRead,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Auth {
+    pub policy: AuthPolicy,
+    /// Mark the session cookie as `Secure`, enable when served over HTTPS.
+    pub secure_cookie: bool,
+    /// Role assigned to new users.
+    pub default_role: Role,
+    /// GitHub logins that are always admins.
+    pub admins: Vec<String>,
+    /// Origins other than the wiki host allowed to send requests that
+    /// change state, for example when served behind a proxy.
+    pub trusted_origins: Vec<String>,
+}
+
+/// Token bucket size and refill rate.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Limit {
+    /// Requests allowed in a burst.
+    pub burst: u32,
+    /// Requests allowed per minute once the burst is spent.
+    pub per_minute: u32,
+}
+
+impl Limit {
+    pub fn per_second(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+/// Rate limits per signed in user, or per client address for
+/// anonymous requests.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    pub enabled: bool,
+    /// Requests that change state.
+    pub write: Limit,
+    /// Full text search.
+    pub search: Limit,
+    /// GitHub sign in and callback.
+    pub login: Limit,
+}
+
+impl RateLimit {
+    pub fn get(&self, class: LimitClass) -> Limit {
+        match class {
+            LimitClass::Write => self.write,
+            LimitClass::Search => self.search,
+            LimitClass::Login => self.login,
+        }
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            write: Limit {
+                burst: 30,
+                per_minute: 60,
+            },
+            search: Limit {
+                burst: 20,
+                per_minute: 120,
+            },
+            login: Limit {
+                burst: 5,
+                per_minute: 10,
+            },
+        }
+    }
+}
+
+/// Tokenizer used by the full-text search index.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tokenizer {
+    /// Match any sequence of three or more characters.
+    #[default]
+    Trigram,
+    /// Match whole words, ignoring case and diacritics.
+    Unicode61,
+    /// Match whole words and their English stems.
+    Porter,
+}
+
+impl Tokenizer {
+    /// Arguments for the FTS5 `tokenize` option.
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Trigram => "trigram",
+            Self::Unicode61 => "unicode61 remove_diacritics 2",
+            Self::Porter => "porter unicode61 remove_diacritics 2",
         }
     }
 }
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Search {
+    /// Changes take effect once the index is rebuilt with `reindex`.
+    pub tokenizer: Tokenizer,
+}
+
+/// Endpoint receiving signed JSON payloads for page and file events.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature of the payload sent in the
+    /// `X-Twilite-Signature` header.
+    pub secret: String,
+    /// Events to deliver, such as `page.updated`, or all when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
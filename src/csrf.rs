@@ -0,0 +1,91 @@
+//! Cross-site request forgery protection.
+//!
+//! Browsers send `Sec-Fetch-Site` and `Origin` headers with form
+//! posts and `fetch()` calls, requests that change state must come
+//! from the same origin unless they authenticate with an API token.
+use crate::{auth::bearer_token, error::ServerError, server::ServerState};
+use axum::{
+    Extension,
+    extract::Request,
+    http::{Method, header},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Determine if an `Origin` header refers to the given host.
+fn origin_matches(origin: &str, host: &str) -> bool {
+    origin
+        .split_once("://")
+        .map(|(_, authority)| authority.trim_end_matches('/').eq_ignore_ascii_case(host))
+        .unwrap_or(false)
+}
+
+/// Reject cross-site requests that mutate state.
+pub async fn protect(
+    Extension(state): Extension<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    if safe || bearer_token(&request).is_some() {
+        return Ok(next.run(request).await);
+    }
+
+    let headers = request.headers();
+    let header_str = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let fetch_site = header_str("sec-fetch-site");
+    let origin = header_str(header::ORIGIN.as_str());
+    let allowed = match (fetch_site, origin) {
+        (Some("same-origin" | "none"), _) => true,
+        (_, Some(origin)) => {
+            let host = header_str(header::HOST.as_str()).unwrap_or_default();
+            origin_matches(origin, host)
+                || state
+                    .config
+                    .auth
+                    .trusted_origins
+                    .iter()
+                    .any(|trusted| trusted.trim_end_matches('/') == origin)
+        }
+        (Some(_), None) => false,
+        // not sent by a browser
+        (None, None) => true,
+    };
+
+    if allowed {
+        Ok(next.run(request).await)
+    } else {
+        tracing::warn!(
+            method = %request.method(),
+            path = %request.uri().path(),
+            "csrf::rejected",
+        );
+        Err(ServerError::Forbidden)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::origin_matches;
+
+    #[test]
+    fn same_origin() {
+        assert!(origin_matches("http://localhost:8776", "localhost:8776"));
+        assert!(origin_matches(
+            "https://Wiki.Example.com",
+            "wiki.example.com"
+        ));
+    }
+
+    #[test]
+    fn cross_origin() {
+        assert!(!origin_matches("https://evil.example", "wiki.example.com"));
+        assert!(!origin_matches("http://localhost:9000", "localhost:8776"));
+        assert!(!origin_matches("null", "localhost:8776"));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{
+    entity::audit::{Actor, AuditAction, AuditEntity},
+    error::ServerError,
+};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+/// Permission granted by an access entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// View the page and its files.
+    Read,
+    /// View and edit the page.
+    Edit,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Edit => "edit",
+        }
+    }
+}
+
+/// Principal used to evaluate access entries.
+///
+/// Pages without access entries are unrestricted, once a page
+/// or one of its name prefixes has entries only the listed users
+/// and groups (and admins) may access the page. A prefix covers the
+/// page with that name and the pages below it, `Team` covers `Team`
+/// and `Team/Notes` but not `Teamwork`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Access {
+    pub user_id: Option<i32>,
+    pub admin: bool,
+}
+
+impl Access {
+    /// SQL condition matching pages readable by this principal.
+    pub fn read_condition(&self, alias: &str) -> Option<String> {
+        self.condition(
+            &format!("{alias}.page_id"),
+            &format!("{alias}.page_name"),
+            Permission::Read,
+        )
+    }
+
+    /// SQL condition matching pages editable by this principal.
+    pub fn edit_condition(&self, alias: &str) -> Option<String> {
+        self.condition(
+            &format!("{alias}.page_id"),
+            &format!("{alias}.page_name"),
+            Permission::Edit,
+        )
+    }
+
+    /// Build a condition for the page identified by the `page_id`
+    /// and `page_name` SQL expressions.
+    ///
+    /// The user id is an integer so it is safe to inline.
+    fn condition(&self, page_id: &str, page_name: &str, permission: Permission) -> Option<String> {
+        if self.admin {
+            return None;
+        }
+
+        let matches = format!(
+            "(a.page_id = {page_id} OR {page_name} = a.name_prefix OR substr({page_name}, 1, length(a.name_prefix) + 1) = a.name_prefix || '/')"
+        );
+        let grants = match self.user_id {
+            Some(user_id) => {
+                let principal = format!(
+                    "(a.user_id = {user_id} OR a.group_id IN (SELECT group_id FROM user_group_members WHERE user_id = {user_id}))"
+                );
+                match permission {
+                    Permission::Read => principal,
+                    Permission::Edit => format!("{principal} AND a.acl_permission = 'edit'"),
+                }
+            }
+            None => "0".to_owned(),
+        };
+
+        Some(format!(
+            "(NOT EXISTS (SELECT 1 FROM page_acls a WHERE {matches}) OR EXISTS (SELECT 1 FROM page_acls a WHERE {matches} AND {grants}))"
+        ))
+    }
+}
+
+/// Target of a new access entry.
+pub enum AclTarget {
+    Page(String),
+    Prefix(String),
+}
+
+/// Principal of a new access entry.
+pub enum AclPrincipal {
+    User(Uuid),
+    Group(Uuid),
+}
+
+pub struct AclEntity {
+    pub acl_id: i32,
+    pub created_at: String,
+    pub acl_uuid: Uuid,
+    pub page_name: Option<String>,
+    pub name_prefix: Option<String>,
+    pub user_login: Option<String>,
+    pub group_name: Option<String>,
+    pub acl_permission: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclResponse {
+    acl_uuid: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_login: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group_name: Option<String>,
+    permission: String,
+}
+
+impl From<AclEntity> for AclResponse {
+    fn from(value: AclEntity) -> Self {
+        Self {
+            acl_uuid: value.acl_uuid,
+            page_name: value.page_name,
+            name_prefix: value.name_prefix,
+            user_login: value.user_login,
+            group_name: value.group_name,
+            permission: value.acl_permission,
+        }
+    }
+}
+
+impl AclEntity {
+    pub async fn find_all(pool: &Pool) -> Result<Vec<Self>, ServerError> {
+        let sql = sql::Select::new()
+            .select(
+                "a.acl_id, a.created_at, a.acl_uuid, p.page_name, a.name_prefix, u.user_login, g.group_name, a.acl_permission",
+            )
+            .from("page_acls a")
+            .left_join("pages p ON a.page_id = p.page_id")
+            .left_join("users u ON a.user_id = u.user_id")
+            .left_join("user_groups g ON a.group_id = g.group_id")
+            .order_by("a.created_at ASC");
+
+        let acls = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&sql.as_string())?;
+                let mut rows = stmt.query([])?;
+                let mut acls = Vec::new();
+                while let Some(row) = rows.next()? {
+                    let acl_uuid = row.get::<_, String>("acl_uuid")?;
+                    let acl_uuid = acl_uuid.parse().unwrap();
+                    acls.push(AclEntity {
+                        acl_id: row.get("acl_id")?,
+                        created_at: row.get("created_at")?,
+                        acl_uuid,
+                        page_name: row.get("page_name")?,
+                        name_prefix: row.get("name_prefix")?,
+                        user_login: row.get("user_login")?,
+                        group_name: row.get("group_name")?,
+                        acl_permission: row.get("acl_permission")?,
+                    });
+                }
+                Ok(acls)
+            })
+            .await?;
+        Ok(acls)
+    }
+
+    pub async fn add(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        target: AclTarget,
+        principal: AclPrincipal,
+        permission: Permission,
+    ) -> Result<Uuid, ServerError> {
+        let select_page = sql::Select::new()
+            .select("page_id")
+            .from("pages")
+            .where_clause("page_name = ?1");
+
+        let select_user = sql::Select::new()
+            .select("user_id")
+            .from("users")
+            .where_clause("user_uuid = ?1");
+
+        let select_group = sql::Select::new()
+            .select("group_id")
+            .from("user_groups")
+            .where_clause("group_uuid = ?1");
+
+        let insert = sql::Insert::new()
+            .insert_into(
+                "page_acls (created_at, acl_uuid, page_id, name_prefix, user_id, group_id, acl_permission)",
+            )
+            .values("(?1, ?2, ?3, ?4, ?5, ?6, ?7)");
+
+        let detail = format!(
+            "{} {} {}",
+            match &target {
+                AclTarget::Page(page_name) => format!("page:{}", page_name),
+                AclTarget::Prefix(prefix) => format!("prefix:{}", prefix),
+            },
+            match &principal {
+                AclPrincipal::User(user_uuid) => format!("user:{}", user_uuid),
+                AclPrincipal::Group(group_uuid) => format!("group:{}", group_uuid),
+            },
+            permission.as_str(),
+        );
+
+        let actor = actor.into();
+        let created_at = UtcDateTime::now().format(&Rfc3339)?;
+        let acl_uuid = Uuid::new_v4();
+        let content: Result<(), async_sqlite::Error> = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let (page_id, name_prefix) = match target {
+                    AclTarget::Page(page_name) => {
+                        let page_id: i32 = tx
+                            .prepare_cached(&select_page.as_string())?
+                            .query_row([page_name], |row| row.get(0))?;
+                        (Some(page_id), None)
+                    }
+                    AclTarget::Prefix(prefix) => (None, Some(prefix)),
+                };
+                let (user_id, group_id) = match principal {
+                    AclPrincipal::User(user_uuid) => {
+                        let user_id: i32 = tx
+                            .prepare_cached(&select_user.as_string())?
+                            .query_row([user_uuid.to_string()], |row| row.get(0))?;
+                        (Some(user_id), None)
+                    }
+                    AclPrincipal::Group(group_uuid) => {
+                        let group_id: i32 = tx
+                            .prepare_cached(&select_group.as_string())?
+                            .query_row([group_uuid.to_string()], |row| row.get(0))?;
+                        (None, Some(group_id))
+                    }
+                };
+                tx.execute(
+                    &insert.as_string(),
+                    (
+                        created_at,
+                        acl_uuid.to_string(),
+                        page_id,
+                        name_prefix,
+                        user_id,
+                        group_id,
+                        permission.as_str(),
+                    ),
+                )?;
+                AuditEntity::record(
+                    &tx,
+                    &actor,
+                    AuditAction::AclCreate,
+                    &acl_uuid.to_string(),
+                    Some(&detail),
+                )?;
+                tx.commit()?;
+                Ok(())
+            })
+            .await;
+
+        match content {
+            Ok(_) => Ok(acl_uuid),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn delete(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        acl_uuid: Uuid,
+    ) -> Result<(), ServerError> {
+        let query = sql::Delete::new()
+            .delete_from("page_acls")
+            .where_clause("acl_uuid = ?1");
+
+        let actor = actor.into();
+        let affected = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let affected = tx.execute(&query.as_string(), [acl_uuid.to_string()])?;
+                if affected > 0 {
+                    AuditEntity::record(
+                        &tx,
+                        &actor,
+                        AuditAction::AclDelete,
+                        &acl_uuid.to_string(),
+                        None,
+                    )?;
+                }
+                tx.commit()?;
+                Ok(affected)
+            })
+            .await?;
+
+        if affected == 0 {
+            Err(ServerError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Determine if a page may be edited.
+    ///
+    /// Both the existing page (when `page_uuid` is given) and the
+    /// target page name must be editable so pages cannot be moved
+    /// into or out of a restricted namespace.
+    pub async fn can_edit(
+        pool: &Pool,
+        page_uuid: Option<Uuid>,
+        page_name: String,
+        access: Access,
+    ) -> Result<bool, ServerError> {
+        let Some(existing) = access.edit_condition("p") else {
+            return Ok(true);
+        };
+        let target = access
+            .condition("NULL", "?1", Permission::Edit)
+            .unwrap_or_default();
+
+        let existing = sql::Select::new()
+            .select("1")
+            .from("pages p")
+            .where_clause("p.page_uuid = ?1")
+            .where_clause(&format!("NOT {existing}"));
+        let target = sql::Select::new().select(&target);
+
+        let allowed = pool
+            .conn(move |conn| {
+                if let Some(page_uuid) = page_uuid {
+                    let denied = conn
+                        .prepare_cached(&existing.as_string())?
+                        .exists([page_uuid.to_string()])?;
+                    if denied {
+                        return Ok(false);
+                    }
+                }
+                conn.prepare_cached(&target.as_string())?
+                    .query_row([page_name], |row| row.get::<_, bool>(0))
+            })
+            .await?;
+        Ok(allowed)
+    }
+}
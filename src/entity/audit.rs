@@ -0,0 +1,231 @@
+use crate::{auth::Identity, entity::user::UserEntity, error::ServerError};
+use async_sqlite::{Pool, rusqlite};
+use sql_query_builder as sql;
+use std::net::IpAddr;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+/// Action recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    PageCreate,
+    PageUpdate,
+    PageRename,
+    PageDelete,
+    FileUpload,
+    Login,
+    Logout,
+    RoleGrant,
+    RoleRevoke,
+    GroupCreate,
+    GroupDelete,
+    GroupMemberAdd,
+    GroupMemberRemove,
+    AclCreate,
+    AclDelete,
+    TokenCreate,
+    TokenRevoke,
+    SearchReindex,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PageCreate => "page.create",
+            Self::PageUpdate => "page.update",
+            Self::PageRename => "page.rename",
+            Self::PageDelete => "page.delete",
+            Self::FileUpload => "file.upload",
+            Self::Login => "auth.login",
+            Self::Logout => "auth.logout",
+            Self::RoleGrant => "role.grant",
+            Self::RoleRevoke => "role.revoke",
+            Self::GroupCreate => "group.create",
+            Self::GroupDelete => "group.delete",
+            Self::GroupMemberAdd => "group.member.add",
+            Self::GroupMemberRemove => "group.member.remove",
+            Self::AclCreate => "acl.create",
+            Self::AclDelete => "acl.delete",
+            Self::TokenCreate => "token.create",
+            Self::TokenRevoke => "token.revoke",
+            Self::SearchReindex => "search.reindex",
+        }
+    }
+}
+
+/// Who performed an audited action.
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+    pub user_id: Option<i32>,
+    pub user_login: Option<String>,
+    pub client_ip: Option<IpAddr>,
+}
+
+impl Actor {
+    pub fn new(user: &UserEntity, client_ip: Option<IpAddr>) -> Self {
+        Self {
+            user_id: Some(user.user_id),
+            user_login: Some(user.user_login.clone()),
+            client_ip,
+        }
+    }
+}
+
+impl From<&Identity> for Actor {
+    fn from(value: &Identity) -> Self {
+        Self {
+            user_id: value.user.as_ref().map(|user| user.user_id),
+            user_login: value.user.as_ref().map(|user| user.user_login.clone()),
+            client_ip: value.client_ip,
+        }
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct AuditQuery {
+    /// Login of the user that performed the action.
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    /// Only entries created at or after this RFC3339 timestamp.
+    pub since: Option<String>,
+    /// Only entries created before this RFC3339 timestamp.
+    pub until: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+pub struct AuditEntity {
+    pub audit_id: i64,
+    pub created_at: String,
+    pub user_login: Option<String>,
+    pub client_ip: Option<String>,
+    pub audit_action: String,
+    pub audit_target: String,
+    pub audit_detail: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditResponse {
+    audit_id: i64,
+    created_at: String,
+    user_login: Option<String>,
+    client_ip: Option<String>,
+    action: String,
+    target: String,
+    detail: Option<String>,
+}
+
+impl From<AuditEntity> for AuditResponse {
+    fn from(value: AuditEntity) -> Self {
+        Self {
+            audit_id: value.audit_id,
+            created_at: value.created_at,
+            user_login: value.user_login,
+            client_ip: value.client_ip,
+            action: value.audit_action,
+            target: value.audit_target,
+            detail: value.audit_detail,
+        }
+    }
+}
+
+impl AuditEntity {
+    /// Record an action on the connection or transaction making the
+    /// change, so the entry is only kept when the change is.
+    pub fn record(
+        conn: &rusqlite::Connection,
+        actor: &Actor,
+        action: AuditAction,
+        target: &str,
+        detail: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        let created_at = UtcDateTime::now()
+            .format(&Rfc3339)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        Self::record_at(conn, actor, action, target, detail, &created_at)
+    }
+
+    /// Record an action at a given time, entries of the same change
+    /// share the time of the change.
+    pub fn record_at(
+        conn: &rusqlite::Connection,
+        actor: &Actor,
+        action: AuditAction,
+        target: &str,
+        detail: Option<&str>,
+        created_at: &str,
+    ) -> rusqlite::Result<()> {
+        let query = sql::Insert::new()
+            .insert_into(
+                "audit_log (created_at, user_id, user_login, client_ip, audit_action, audit_target, audit_detail)",
+            )
+            .values("(?1, ?2, ?3, ?4, ?5, ?6, ?7)");
+
+        let mut stmt = conn.prepare_cached(&query.as_string())?;
+        stmt.execute((
+            created_at,
+            actor.user_id,
+            &actor.user_login,
+            actor.client_ip.map(|ip| ip.to_string()),
+            action.as_str(),
+            target,
+            detail,
+        ))?;
+        Ok(())
+    }
+
+    pub async fn find_all(pool: &Pool, query: AuditQuery) -> Result<Vec<Self>, ServerError> {
+        let mut sql = sql::Select::new()
+            .select(
+                "audit_id, created_at, user_login, client_ip, audit_action, audit_target, audit_detail",
+            )
+            .from("audit_log");
+
+        let mut params = Vec::new();
+        let filters = [
+            ("user_login = ", query.actor),
+            ("audit_action = ", query.action),
+            ("audit_target = ", query.target),
+            ("created_at >= ", query.since),
+            ("created_at < ", query.until),
+        ];
+        for (condition, value) in filters {
+            if let Some(value) = value {
+                params.push(value);
+                sql = sql.where_clause(&format!("{}?{}", condition, params.len()));
+            }
+        }
+
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let offset = query.offset.unwrap_or_default();
+        let sql = sql.order_by(&format!(
+            "created_at DESC, audit_id DESC LIMIT {} OFFSET {}",
+            limit, offset
+        ));
+
+        let entries = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&sql.as_string())?;
+                let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+                let mut entries = Vec::new();
+                while let Some(row) = rows.next()? {
+                    entries.push(AuditEntity {
+                        audit_id: row.get("audit_id")?,
+                        created_at: row.get("created_at")?,
+                        user_login: row.get("user_login")?,
+                        client_ip: row.get("client_ip")?,
+                        audit_action: row.get("audit_action")?,
+                        audit_target: row.get("audit_target")?,
+                        audit_detail: row.get("audit_detail")?,
+                    });
+                }
+                Ok(entries)
+            })
+            .await?;
+        Ok(entries)
+    }
+}
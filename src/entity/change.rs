@@ -0,0 +1,269 @@
+use crate::{entity::acl::Access, error::ServerError};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use uuid::Uuid;
+
+/// Audit actions that change a page.
+const PAGE_ACTIONS: &str =
+    "('page.create', 'page.update', 'page.rename', 'page.delete', 'file.upload')";
+
+/// Change to a page taken from the audit log. Changes are listed under
+/// the current name of the page, or the name it had when deleted.
+///
+/// The update and rename recorded by the same edit share their time,
+/// the update is left out so the edit is listed once.
+pub struct ChangeEntity {
+    pub audit_id: i64,
+    pub changed_at: String,
+    pub change_action: String,
+    pub change_detail: Option<String>,
+    pub user_login: Option<String>,
+    pub page_uuid: Uuid,
+    pub page_name: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeResponse {
+    change_id: i64,
+    changed_at: String,
+    action: String,
+    detail: Option<String>,
+    user_login: Option<String>,
+    page_uuid: Uuid,
+    page_name: String,
+}
+
+impl From<ChangeEntity> for ChangeResponse {
+    fn from(value: ChangeEntity) -> Self {
+        Self {
+            change_id: value.audit_id,
+            changed_at: value.changed_at,
+            action: value.change_action,
+            detail: value.change_detail,
+            user_login: value.user_login,
+            page_uuid: value.page_uuid,
+            page_name: value.page_name,
+        }
+    }
+}
+
+impl ChangeEntity {
+    /// Changes readable with `access`, only those of the page with the
+    /// uuid bound to `?1` when `page` is set.
+    fn select(access: &Access, page: bool) -> sql::Select {
+        let mut changes = sql::Select::new()
+            .select(
+                "l.audit_id, l.created_at, l.audit_action, l.audit_detail, l.user_login, \
+                l.audit_target AS page_uuid, p.page_id, COALESCE(p.page_name, d.audit_detail) AS page_name",
+            )
+            .from("audit_log l")
+            .left_join("pages p ON p.page_uuid = l.audit_target")
+            .left_join("audit_log d ON d.audit_target = l.audit_target AND d.audit_action = 'page.delete'")
+            .where_clause(&format!("l.audit_action IN {}", PAGE_ACTIONS))
+            .where_clause(
+                "NOT (l.audit_action = 'page.update' AND EXISTS (SELECT 1 FROM audit_log r \
+                WHERE r.audit_target = l.audit_target AND r.audit_action = 'page.rename' AND r.created_at = l.created_at))",
+            );
+        if page {
+            changes = changes.where_clause("l.audit_target = ?1");
+        }
+        let mut query = sql::Select::new()
+            .select("c.*")
+            .from(&format!("({}) c", changes.as_string()))
+            .where_clause("c.page_name IS NOT NULL");
+        if let Some(condition) = access.read_condition("c") {
+            query = query.where_clause(&condition);
+        }
+        query
+    }
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let page_uuid = row.get::<_, String>("page_uuid")?;
+        Ok(Self {
+            audit_id: row.get("audit_id")?,
+            changed_at: row.get("created_at")?,
+            change_action: row.get("audit_action")?,
+            change_detail: row.get("audit_detail")?,
+            user_login: row.get("user_login")?,
+            page_uuid: page_uuid.parse().unwrap(),
+            page_name: row.get("page_name")?,
+        })
+    }
+
+    /// Changes to the pages a user watches, latest first, optionally
+    /// only those after `since`.
+    pub async fn find_watched(
+        pool: &Pool,
+        user_id: i32,
+        since: Option<String>,
+        limit: u32,
+        access: Access,
+    ) -> Result<Vec<Self>, ServerError> {
+        let query = Self::select(&access, false)
+            .where_clause(
+                "EXISTS (SELECT 1 FROM watches w WHERE w.user_id = ?1 AND (w.page_name = c.page_name \
+                OR (w.include_subpages AND substr(c.page_name, 1, length(w.page_name) + 1) = w.page_name || '/')))",
+            )
+            .where_clause("(?2 IS NULL OR julianday(c.created_at) > julianday(?2))")
+            .order_by(&format!("c.audit_id DESC LIMIT {}", limit));
+
+        let changes = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query((user_id, since))?;
+                let mut changes = Vec::new();
+                while let Some(row) = rows.next()? {
+                    changes.push(Self::from_row(row)?);
+                }
+                Ok(changes)
+            })
+            .await?;
+        Ok(changes)
+    }
+
+    /// Latest changes to a page by name, looked up by the uuid of the
+    /// page so only its own audit entries are read.
+    pub async fn find_by_page_name(
+        pool: &Pool,
+        page_name: String,
+        limit: u32,
+        access: Access,
+    ) -> Result<Vec<Self>, ServerError> {
+        let mut page = sql::Select::new()
+            .select("page_uuid")
+            .from("pages")
+            .where_clause("page_name = ?1");
+        if let Some(condition) = access.read_condition("pages") {
+            page = page.where_clause(&condition);
+        }
+        let query =
+            Self::select(&access, true).order_by(&format!("c.audit_id DESC LIMIT {}", limit));
+
+        let content: Result<Vec<Self>, async_sqlite::Error> = pool
+            .conn(move |conn| {
+                // an unreadable page is reported as missing
+                let page_uuid: String = conn
+                    .prepare_cached(&page.as_string())?
+                    .query_row([&page_name], |row| row.get(0))?;
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([&page_uuid])?;
+                let mut changes = Vec::new();
+                while let Some(row) = rows.next()? {
+                    changes.push(Self::from_row(row)?);
+                }
+                Ok(changes)
+            })
+            .await;
+
+        match content {
+            Ok(changes) => Ok(changes),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChangeEntity;
+    use crate::{
+        entity::{
+            acl::Access,
+            audit::{Actor, AuditAction, AuditEntity},
+            page::{PageEntity, PageProperties},
+            watch::WatchEntity,
+        },
+        migrations::test_pool,
+    };
+
+    fn actions(changes: &[ChangeEntity]) -> Vec<(&str, &str)> {
+        changes
+            .iter()
+            .map(|change| (change.change_action.as_str(), change.page_name.as_str()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn lists_renames_once_and_deleted_pages() {
+        let pool = test_pool().await;
+        let add = |page_name: &str| {
+            PageEntity::add(
+                &pool,
+                Actor::default(),
+                page_name.to_owned(),
+                "<p>text</p>".to_owned(),
+                vec![],
+                PageProperties::new(),
+                vec![],
+            )
+        };
+        let runbook = add("Team/Runbook").await.unwrap();
+        let notes = add("Team/Notes").await.unwrap();
+        PageEntity::edit(
+            &pool,
+            Actor::default(),
+            runbook,
+            "Team/Oncall".to_owned(),
+            "<p>more text</p>".to_owned(),
+            None,
+            None,
+            vec![],
+        )
+        .await
+        .unwrap();
+        pool.conn(move |conn| {
+            let actor = Actor::default();
+            let target = notes.to_string();
+            // an unrelated entry written between the update and the rename
+            let at = "2025-01-01T00:00:00Z";
+            let update = AuditAction::PageUpdate;
+            AuditEntity::record_at(conn, &actor, update, &target, Some("Team/Notes"), at)?;
+            AuditEntity::record(conn, &actor, AuditAction::Login, "user", None)?;
+            let rename = AuditAction::PageRename;
+            AuditEntity::record_at(
+                conn,
+                &actor,
+                rename,
+                &target,
+                Some("Notes -> Team/Notes"),
+                at,
+            )?;
+            let delete = AuditAction::PageDelete;
+            AuditEntity::record(conn, &actor, delete, &target, Some("Team/Notes"))?;
+            conn.execute("DELETE FROM pages WHERE page_uuid = ?1", [&target])?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        WatchEntity::watch(&pool, 1, "Team".to_owned(), true)
+            .await
+            .unwrap();
+
+        let changes = ChangeEntity::find_watched(&pool, 1, None, 50, Access::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            actions(&changes),
+            [
+                ("page.delete", "Team/Notes"),
+                ("page.rename", "Team/Notes"),
+                ("page.rename", "Team/Oncall"),
+                ("page.create", "Team/Notes"),
+                ("page.create", "Team/Oncall"),
+            ]
+        );
+
+        let changes =
+            ChangeEntity::find_by_page_name(&pool, "Team/Oncall".to_owned(), 50, Access::default())
+                .await
+                .unwrap();
+        assert_eq!(
+            actions(&changes),
+            [
+                ("page.rename", "Team/Oncall"),
+                ("page.create", "Team/Oncall")
+            ]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{entity::acl::Access, error::ServerError};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use uuid::Uuid;
+
+/// User that has edited a page.
+pub struct ContributorEntity {
+    pub user_uuid: Uuid,
+    pub user_login: String,
+    pub user_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub first_edit_at: String,
+    pub last_edit_at: String,
+    pub edit_count: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContributorResponse {
+    user_uuid: Uuid,
+    user_login: String,
+    user_name: Option<String>,
+    avatar_url: Option<String>,
+    first_edit_at: String,
+    last_edit_at: String,
+    edit_count: i64,
+}
+
+impl From<ContributorEntity> for ContributorResponse {
+    fn from(value: ContributorEntity) -> Self {
+        Self {
+            user_uuid: value.user_uuid,
+            user_login: value.user_login,
+            user_name: value.user_name,
+            avatar_url: value.avatar_url,
+            first_edit_at: value.first_edit_at,
+            last_edit_at: value.last_edit_at,
+            edit_count: value.edit_count,
+        }
+    }
+}
+
+impl ContributorEntity {
+    /// Find everyone that has edited a page, most active first.
+    pub async fn find_by_page_name(
+        pool: &Pool,
+        page_name: String,
+        access: Access,
+    ) -> Result<Vec<Self>, ServerError> {
+        let mut page = sql::Select::new()
+            .select("page_id")
+            .from("pages")
+            .where_clause("page_name = ?1");
+        if let Some(condition) = access.read_condition("pages") {
+            page = page.where_clause(&condition);
+        }
+
+        let query = sql::Select::new()
+            .select("u.user_uuid, u.user_login, u.user_name, u.avatar_url, c.first_edit_at, c.last_edit_at, c.edit_count")
+            .from("page_contributors c")
+            .inner_join("users u ON u.user_id = c.user_id")
+            .where_clause("c.page_id = ?1")
+            .order_by("c.edit_count DESC, c.last_edit_at DESC");
+
+        let content: Result<Vec<Self>, async_sqlite::Error> = pool
+            .conn(move |conn| {
+                let page_id: i64 = conn
+                    .prepare_cached(&page.as_string())?
+                    .query_row([page_name], |row| row.get(0))?;
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([page_id])?;
+                let mut contributors = Vec::new();
+                while let Some(row) = rows.next()? {
+                    let user_uuid = row.get::<_, String>("user_uuid")?;
+                    let user_uuid = user_uuid.parse().unwrap();
+                    contributors.push(ContributorEntity {
+                        user_uuid,
+                        user_login: row.get("user_login")?,
+                        user_name: row.get("user_name")?,
+                        avatar_url: row.get("avatar_url")?,
+                        first_edit_at: row.get("first_edit_at")?,
+                        last_edit_at: row.get("last_edit_at")?,
+                        edit_count: row.get("edit_count")?,
+                    });
+                }
+                Ok(contributors)
+            })
+            .await;
+
+        match content {
+            Ok(contributors) => Ok(contributors),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
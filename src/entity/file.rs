@@ -1,5 +1,5 @@
-use crate::error::ServerError;
-use async_sqlite::{Client, Error::Rusqlite, rusqlite};
+use crate::{entity::acl::Access, error::ServerError};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
 use sql_query_builder as sql;
 use uuid::Uuid;
 
@@ -37,7 +37,7 @@ impl From<FileEntity> for FileResponse {
 
 impl FileEntity {
     pub async fn find_all_by_page_id(
-        client: &Client,
+        pool: &Pool,
         page_id: i32,
     ) -> Result<Vec<FileEntity>, ServerError> {
         let sql = sql::Select::new()
@@ -47,7 +47,7 @@ impl FileEntity {
             .from("files f")
             .inner_join("page_files pf ON f.file_id = pf.file_id")
             .where_clause("pf.page_id = ?");
-        let files = client
+        let files = pool
             .conn(move |conn| {
                 let mut stmt = conn.prepare_cached(&sql.as_string())?;
                 let mut rows = stmt.query([page_id])?;
@@ -73,15 +73,21 @@ impl FileEntity {
     }
 
     pub async fn find_buffer_by_uuid(
-        client: &Client,
+        pool: &Pool,
         file_uuid: Uuid,
+        access: Access,
     ) -> Result<(usize, String, Vec<u8>), ServerError> {
-        let query = sql::Select::new()
+        let mut query = sql::Select::new()
             .select("file_size, content_type, file_content")
             .from("files")
             .where_clause("file_uuid = ?1");
+        if let Some(condition) = access.read_condition("p") {
+            query = query.where_clause(&format!(
+                "EXISTS (SELECT 1 FROM page_files pf INNER JOIN pages p ON pf.page_id = p.page_id WHERE pf.file_id = files.file_id AND {condition})"
+            ));
+        }
 
-        let content: Result<(usize, String, Vec<u8>), async_sqlite::Error> = client
+        let content: Result<(usize, String, Vec<u8>), async_sqlite::Error> = pool
             .conn(move |conn| {
                 let mut stmt = conn.prepare_cached(&query.as_string())?;
                 stmt.query_row([file_uuid.to_string()], |row| {
@@ -0,0 +1,286 @@
+use crate::{
+    entity::{
+        audit::{Actor, AuditAction, AuditEntity},
+        user::{UserEntity, UserResponse},
+    },
+    error::ServerError,
+};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+pub struct GroupEntity {
+    pub group_id: i32,
+    pub created_at: String,
+    pub group_uuid: Uuid,
+    pub group_name: String,
+    pub members: Vec<UserEntity>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupResponse {
+    group_uuid: Uuid,
+    group_name: String,
+    members: Vec<UserResponse>,
+}
+
+impl From<GroupEntity> for GroupResponse {
+    fn from(value: GroupEntity) -> Self {
+        Self {
+            group_uuid: value.group_uuid,
+            group_name: value.group_name,
+            members: value.members.into_iter().map(UserResponse::from).collect(),
+        }
+    }
+}
+
+impl GroupEntity {
+    pub async fn find_all(pool: &Pool) -> Result<Vec<Self>, ServerError> {
+        let groups_sql = sql::Select::new()
+            .select("group_id, created_at, group_uuid, group_name")
+            .from("user_groups")
+            .order_by("group_name ASC");
+
+        let members_sql = sql::Select::new()
+            .select("m.group_id, u.*")
+            .from("user_group_members m")
+            .inner_join("users u ON m.user_id = u.user_id")
+            .order_by("u.user_login ASC");
+
+        let groups = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&groups_sql.as_string())?;
+                let mut rows = stmt.query([])?;
+                let mut groups = Vec::new();
+                while let Some(row) = rows.next()? {
+                    let group_uuid = row.get::<_, String>("group_uuid")?;
+                    let group_uuid = group_uuid.parse().unwrap();
+                    groups.push(GroupEntity {
+                        group_id: row.get("group_id")?,
+                        created_at: row.get("created_at")?,
+                        group_uuid,
+                        group_name: row.get("group_name")?,
+                        members: Vec::new(),
+                    });
+                }
+
+                let mut stmt = conn.prepare_cached(&members_sql.as_string())?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    let group_id: i32 = row.get("group_id")?;
+                    if let Some(group) = groups.iter_mut().find(|g| g.group_id == group_id) {
+                        group.members.push(UserEntity::from_row(row)?);
+                    }
+                }
+                Ok(groups)
+            })
+            .await?;
+        Ok(groups)
+    }
+
+    pub async fn create(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        group_name: String,
+    ) -> Result<Self, ServerError> {
+        let query = sql::Insert::new()
+            .insert_into("user_groups (created_at, group_uuid, group_name)")
+            .values("(?1, ?2, ?3)");
+
+        let created_at = UtcDateTime::now().format(&Rfc3339)?;
+        let group_uuid = Uuid::new_v4();
+        let entity = GroupEntity {
+            group_id: 0,
+            created_at: created_at.clone(),
+            group_uuid,
+            group_name: group_name.clone(),
+            members: Vec::new(),
+        };
+
+        let actor = actor.into();
+        match pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    &query.as_string(),
+                    (created_at, group_uuid.to_string(), &group_name),
+                )?;
+                let group_id = tx.last_insert_rowid();
+                AuditEntity::record(
+                    &tx,
+                    &actor,
+                    AuditAction::GroupCreate,
+                    &group_uuid.to_string(),
+                    Some(&group_name),
+                )?;
+                tx.commit()?;
+                Ok(group_id)
+            })
+            .await
+        {
+            Ok(group_id) => Ok(GroupEntity {
+                group_id: group_id as i32,
+                ..entity
+            }),
+            Err(Rusqlite(rusqlite::Error::SqliteFailure(err, _)))
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Err(ServerError::Conflict)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Delete a group, its memberships and access entries.
+    pub async fn delete(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        group_uuid: Uuid,
+    ) -> Result<(), ServerError> {
+        let select = sql::Select::new()
+            .select("group_id")
+            .from("user_groups")
+            .where_clause("group_uuid = ?1");
+
+        let actor = actor.into();
+        let content: Result<(), async_sqlite::Error> = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let group_id: i32 = tx
+                    .prepare_cached(&select.as_string())?
+                    .query_row([group_uuid.to_string()], |row| row.get(0))?;
+                for table in ["page_acls", "user_group_members", "user_groups"] {
+                    let query = sql::Delete::new()
+                        .delete_from(table)
+                        .where_clause("group_id = ?1");
+                    tx.execute(&query.as_string(), [group_id])?;
+                }
+                AuditEntity::record(
+                    &tx,
+                    &actor,
+                    AuditAction::GroupDelete,
+                    &group_uuid.to_string(),
+                    None,
+                )?;
+                tx.commit()?;
+                Ok(())
+            })
+            .await;
+
+        match content {
+            Ok(_) => Ok(()),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn add_member(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        group_uuid: Uuid,
+        user_uuid: Uuid,
+    ) -> Result<(), ServerError> {
+        let exists = sql::Select::new()
+            .select("1")
+            .from("user_group_members")
+            .where_clause("group_id = ?1")
+            .where_clause("user_id = ?2");
+
+        let insert = sql::Insert::new()
+            .insert_into("user_group_members (group_id, user_id)")
+            .values("(?1, ?2)");
+
+        Self::change_member(
+            pool,
+            actor.into(),
+            AuditAction::GroupMemberAdd,
+            group_uuid,
+            user_uuid,
+            move |tx, ids| {
+                if !tx.prepare_cached(&exists.as_string())?.exists(ids)? {
+                    tx.execute(&insert.as_string(), ids)?;
+                }
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    pub async fn remove_member(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        group_uuid: Uuid,
+        user_uuid: Uuid,
+    ) -> Result<(), ServerError> {
+        let delete = sql::Delete::new()
+            .delete_from("user_group_members")
+            .where_clause("group_id = ?1")
+            .where_clause("user_id = ?2");
+
+        Self::change_member(
+            pool,
+            actor.into(),
+            AuditAction::GroupMemberRemove,
+            group_uuid,
+            user_uuid,
+            move |tx, ids| {
+                tx.execute(&delete.as_string(), ids)?;
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    /// Resolve the group and user then apply and record a membership change.
+    async fn change_member<F>(
+        pool: &Pool,
+        actor: Actor,
+        action: AuditAction,
+        group_uuid: Uuid,
+        user_uuid: Uuid,
+        change: F,
+    ) -> Result<(), ServerError>
+    where
+        F: FnOnce(&rusqlite::Transaction<'_>, (i32, i32)) -> rusqlite::Result<()> + Send + 'static,
+    {
+        let select_group = sql::Select::new()
+            .select("group_id")
+            .from("user_groups")
+            .where_clause("group_uuid = ?1");
+
+        let select_user = sql::Select::new()
+            .select("user_id")
+            .from("users")
+            .where_clause("user_uuid = ?1");
+
+        let content: Result<(), async_sqlite::Error> = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let group_id: i32 = tx
+                    .prepare_cached(&select_group.as_string())?
+                    .query_row([group_uuid.to_string()], |row| row.get(0))?;
+                let user_id: i32 = tx
+                    .prepare_cached(&select_user.as_string())?
+                    .query_row([user_uuid.to_string()], |row| row.get(0))?;
+                change(&tx, (group_id, user_id))?;
+                AuditEntity::record(
+                    &tx,
+                    &actor,
+                    action,
+                    &group_uuid.to_string(),
+                    Some(&user_uuid.to_string()),
+                )?;
+                tx.commit()?;
+                Ok(())
+            })
+            .await;
+
+        match content {
+            Ok(_) => Ok(()),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
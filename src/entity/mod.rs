@@ -1,3 +1,15 @@
+pub mod acl;
+pub mod audit;
+pub mod change;
+pub mod contributor;
 pub mod file;
+pub mod group;
 pub mod page;
+pub mod saved_search;
 pub mod search;
+pub mod session;
+pub mod tag;
+pub mod token;
+pub mod user;
+pub mod watch;
+pub mod webhook_delivery;
@@ -1,17 +1,63 @@
 use crate::{
-    entity::file::{FileEntity, FileResponse},
+    entity::{
+        acl::Access,
+        audit::{Actor, AuditAction, AuditEntity},
+        file::{FileEntity, FileResponse},
+    },
     error::ServerError,
-    helpers::{html_to_text, sanitize_html, stringify_doc, transform_page, trim_preview_text},
+    extract::extract_text,
+    helpers::{
+        encode_cursor, html_to_text, page_ancestors, sanitize_html, stringify_doc,
+        transform_page_in, trigram_similarity, trim_preview_text, wiki_links,
+    },
 };
-use async_sqlite::{Client, Error::Rusqlite, rusqlite};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
 use axum::body::Bytes;
 use sql_query_builder as sql;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use time::{UtcDateTime, format_description::well_known::Rfc3339};
 use uuid::Uuid;
 
 /// Upload for a page.
 pub struct PageUpload(pub String, pub String, pub Bytes);
 
+/// Properties of a page by key.
+pub type PageProperties = BTreeMap<String, String>;
+
+/// Columns selected for a page along with its creator and last editor.
+const PAGE_COLUMNS: &str = "pages.page_id, pages.created_at, pages.updated_at, pages.page_uuid, pages.page_name, pages.page_content, pages.page_text, pages.page_toc, \
+    cu.user_uuid AS created_by_uuid, cu.user_login AS created_by_login, cu.avatar_url AS created_by_avatar_url, \
+    uu.user_uuid AS updated_by_uuid, uu.user_login AS updated_by_login, uu.avatar_url AS updated_by_avatar_url";
+
+/// User that created or edited a page.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageAuthor {
+    user_uuid: Uuid,
+    user_login: String,
+    avatar_url: Option<String>,
+}
+
+impl PageAuthor {
+    pub fn user_login(&self) -> &str {
+        &self.user_login
+    }
+
+    /// Read an author from the columns starting with `prefix`, pages
+    /// saved anonymously or before authors were recorded have none.
+    fn from_row(row: &rusqlite::Row, prefix: &str) -> rusqlite::Result<Option<Self>> {
+        let user_uuid: Option<String> = row.get(format!("{}_uuid", prefix).as_str())?;
+        let Some(user_uuid) = user_uuid else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            user_uuid: user_uuid.parse().unwrap(),
+            user_login: row.get(format!("{}_login", prefix).as_str())?,
+            avatar_url: row.get(format!("{}_avatar_url", prefix).as_str())?,
+        }))
+    }
+}
+
 pub struct PageEntity {
     pub page_id: i32,
     pub created_at: String,
@@ -22,6 +68,31 @@ pub struct PageEntity {
     pub page_text: String,
     pub page_toc: Option<String>,
     pub page_files: Vec<FileEntity>,
+    pub page_tags: Vec<String>,
+    pub page_properties: PageProperties,
+    pub breadcrumbs: Vec<Breadcrumb>,
+    pub created_by: Option<PageAuthor>,
+    pub updated_by: Option<PageAuthor>,
+}
+
+/// Ancestor of a page, which may not exist as a page itself.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Breadcrumb {
+    page_name: String,
+    page_uuid: Option<Uuid>,
+}
+
+/// Page directly below another page, with the number of pages
+/// further below it. There may be no page with the name itself when
+/// only pages below it exist.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageChild {
+    page_name: String,
+    page_uuid: Option<Uuid>,
+    updated_at: Option<String>,
+    descendant_count: i64,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -34,6 +105,11 @@ pub struct PageResponse {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     page_files: Vec<FileResponse>,
     page_toc: Option<String>,
+    page_tags: Vec<String>,
+    page_properties: PageProperties,
+    breadcrumbs: Vec<Breadcrumb>,
+    created_by: Option<PageAuthor>,
+    updated_by: Option<PageAuthor>,
 }
 
 impl From<PageEntity> for PageResponse {
@@ -49,6 +125,11 @@ impl From<PageEntity> for PageResponse {
                 .into_iter()
                 .map(FileResponse::from)
                 .collect(),
+            page_tags: value.page_tags,
+            page_properties: value.page_properties,
+            breadcrumbs: value.breadcrumbs,
+            created_by: value.created_by,
+            updated_by: value.updated_by,
         }
     }
 }
@@ -60,6 +141,7 @@ pub struct PagePreview {
     page_name: String,
     updated_at: String,
     preview_text: String,
+    updated_by: Option<PageAuthor>,
     // #[serde(skip_serializing_if = "Vec::is_empty")]
     // page_files: Vec<FileResponse>,
 }
@@ -71,40 +153,341 @@ impl From<PageEntity> for PagePreview {
             page_name: value.page_name,
             updated_at: value.updated_at,
             preview_text: trim_preview_text(&value.page_text).to_owned(),
+            updated_by: value.updated_by,
         }
     }
 }
 
+/// Page in a listing.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageListing {
+    page_uuid: Uuid,
+    page_name: String,
+    created_at: String,
+    updated_at: String,
+    page_properties: PageProperties,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview_text: Option<String>,
+}
+
+/// Page of a listing with the cursor of the next one, if any.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageList {
+    pages: Vec<PageListing>,
+    next_cursor: Option<String>,
+}
+
+/// Column a page listing is sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PageSort {
+    #[default]
+    Name,
+    Created,
+    Updated,
+}
+
+impl PageSort {
+    pub fn from_param(value: &str) -> Option<Self> {
+        match value {
+            "name" => Some(Self::Name),
+            "created" => Some(Self::Created),
+            "updated" => Some(Self::Updated),
+            _ => None,
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Name => "page_name",
+            Self::Created => "created_at",
+            Self::Updated => "updated_at",
+        }
+    }
+}
+
+/// Filters and order of a page listing.
+#[derive(Debug)]
+pub struct PageListOptions {
+    pub sort: PageSort,
+    pub descending: bool,
+    /// Only list pages with names starting with the prefix.
+    pub prefix: Option<String>,
+    /// Only list pages with all of the property values.
+    pub properties: PageProperties,
+    /// Continue after the last page of a previous listing.
+    pub cursor: Option<(String, i32)>,
+    pub limit: usize,
+    pub include_preview: bool,
+}
+
+/// Page name suggested for a partial name.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageSuggestion {
+    page_uuid: Uuid,
+    page_name: String,
+    updated_at: String,
+    /// Number of pages linking to this page.
+    link_count: i64,
+    #[serde(skip)]
+    rank: u8,
+}
+
+/// Minimum trigram similarity for a name to be suggested when it
+/// neither starts with nor contains the input.
+const SUGGEST_SIMILARITY: f64 = 0.3;
+
+/// Names sharing trigrams with the input that are compared to it.
+const SUGGEST_CANDIDATES: usize = 100;
+
+/// Longest input used for suggestions, longer input is cut.
+const SUGGEST_INPUT_LENGTH: usize = 64;
+
+impl PageSuggestion {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let page_uuid = row.get::<_, String>("page_uuid")?;
+        Ok(Self {
+            page_uuid: page_uuid.parse().unwrap(),
+            page_name: row.get("page_name")?,
+            updated_at: row.get("updated_at")?,
+            link_count: row.get("link_count")?,
+            rank: 0,
+        })
+    }
+}
+
 #[derive(Debug, Default, serde::Deserialize)]
 pub struct PageSelectOptions {
     pub include_files: bool,
 }
 
 impl PageEntity {
+    /// Select pages joined with their creator and last editor.
+    fn select() -> sql::Select {
+        sql::Select::new()
+            .select(PAGE_COLUMNS)
+            .from("pages")
+            .left_join("users cu ON cu.user_id = pages.created_by")
+            .left_join("users uu ON uu.user_id = pages.updated_by")
+    }
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let page_uuid = row.get::<_, String>("page_uuid")?;
+        let page_uuid = page_uuid.parse().unwrap();
+        Ok(PageEntity {
+            page_id: row.get("page_id")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+            page_uuid,
+            page_name: row.get("page_name")?,
+            page_content: row.get("page_content")?,
+            page_text: row.get("page_text")?,
+            page_toc: row.get("page_toc")?,
+            page_files: Vec::new(),
+            page_tags: Vec::new(),
+            page_properties: PageProperties::new(),
+            breadcrumbs: Vec::new(),
+            created_by: PageAuthor::from_row(row, "created_by")?,
+            updated_by: PageAuthor::from_row(row, "updated_by")?,
+        })
+    }
+
+    /// Count an edit towards a contributor of a page.
+    fn add_contributor(
+        tx: &rusqlite::Transaction,
+        page_id: i64,
+        user_id: i32,
+        edited_at: &str,
+    ) -> rusqlite::Result<()> {
+        let query = sql::Insert::new()
+            .insert_into("page_contributors (page_id, user_id, first_edit_at, last_edit_at, edit_count)")
+            .values(
+                "(?1, ?2, ?3, ?3, 1) ON CONFLICT (page_id, user_id) DO UPDATE SET last_edit_at = excluded.last_edit_at, edit_count = edit_count + 1",
+            );
+        tx.execute(&query.as_string(), (page_id, user_id, edited_at))?;
+        Ok(())
+    }
+
+    /// Pair uploads with their searchable text.
+    async fn extract_uploads(uploads: Vec<PageUpload>) -> Vec<(PageUpload, Option<String>)> {
+        let mut extracted = Vec::with_capacity(uploads.len());
+        for upload in uploads {
+            let file_text = extract_text(&upload.0, &upload.1, &upload.2).await;
+            extracted.push((upload, file_text));
+        }
+        extracted
+    }
+
+    /// Store uploads with their extracted text and attach them to a page.
+    fn add_uploads(
+        tx: &rusqlite::Transaction,
+        actor: &Actor,
+        page_id: i64,
+        page_uuid: Uuid,
+        uploads: Vec<(PageUpload, Option<String>)>,
+        uploaded_at: &str,
+    ) -> rusqlite::Result<()> {
+        let insert_file = sql::Insert::new()
+            .insert_into(
+                "files (created_at, updated_at, file_uuid, file_name, file_size, content_type, file_content, file_text)",
+            )
+            .values("(?1, ?1, ?2, ?3, ?4, ?5, ?6, ?7)");
+        let insert_page_file = sql::Insert::new()
+            .insert_into("page_files (page_id, file_id)")
+            .values("(?1, ?2)");
+
+        for (upload, file_text) in uploads {
+            AuditEntity::record(
+                tx,
+                actor,
+                AuditAction::FileUpload,
+                &page_uuid.to_string(),
+                Some(&upload.0),
+            )?;
+            tx.execute(
+                &insert_file.as_string(),
+                (
+                    uploaded_at,
+                    Uuid::new_v4().to_string(),
+                    upload.0,
+                    upload.2.len(),
+                    upload.1,
+                    upload.2.to_vec(),
+                    file_text,
+                ),
+            )?;
+            let file_id = tx.last_insert_rowid();
+            tx.execute(&insert_page_file.as_string(), (page_id, file_id))?;
+        }
+        Ok(())
+    }
+
+    /// Replace the outgoing links of a page.
+    fn save_links(
+        tx: &rusqlite::Transaction,
+        page_id: i64,
+        page_links: &[String],
+    ) -> rusqlite::Result<()> {
+        let delete = sql::Delete::new()
+            .delete_from("page_links")
+            .where_clause("page_id = ?1");
+        let insert = sql::Insert::new()
+            .insert_into("page_links (page_id, target_name)")
+            .values("(?1, ?2)");
+        tx.execute(&delete.as_string(), [page_id])?;
+        let mut stmt = tx.prepare_cached(&insert.as_string())?;
+        for target_name in page_links {
+            stmt.execute((page_id, target_name))?;
+        }
+        Ok(())
+    }
+
+    /// Replace the tags of a page.
+    fn save_tags(
+        tx: &rusqlite::Transaction,
+        page_id: i64,
+        page_tags: &[String],
+    ) -> rusqlite::Result<()> {
+        let delete = sql::Delete::new()
+            .delete_from("page_tags")
+            .where_clause("page_id = ?1");
+        let insert = sql::Insert::new()
+            .insert_into("page_tags (page_id, tag_name)")
+            .values("(?1, ?2)");
+        tx.execute(&delete.as_string(), [page_id])?;
+        let mut stmt = tx.prepare_cached(&insert.as_string())?;
+        for tag_name in page_tags {
+            stmt.execute((page_id, tag_name))?;
+        }
+        Ok(())
+    }
+
+    /// Replace the properties of a page.
+    fn save_properties(
+        tx: &rusqlite::Transaction,
+        page_id: i64,
+        page_properties: &PageProperties,
+    ) -> rusqlite::Result<()> {
+        let delete = sql::Delete::new()
+            .delete_from("page_properties")
+            .where_clause("page_id = ?1");
+        let insert = sql::Insert::new()
+            .insert_into("page_properties (page_id, prop_key, prop_value)")
+            .values("(?1, ?2, ?3)");
+        tx.execute(&delete.as_string(), [page_id])?;
+        let mut stmt = tx.prepare_cached(&insert.as_string())?;
+        for (prop_key, prop_value) in page_properties {
+            stmt.execute((page_id, prop_key, prop_value))?;
+        }
+        Ok(())
+    }
+
+    fn find_properties(
+        conn: &rusqlite::Connection,
+        page_id: i32,
+    ) -> rusqlite::Result<PageProperties> {
+        let query = sql::Select::new()
+            .select("prop_key, prop_value")
+            .from("page_properties")
+            .where_clause("page_id = ?1");
+        let mut stmt = conn.prepare_cached(&query.as_string())?;
+        let properties = stmt
+            .query_map([page_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<PageProperties>>()?;
+        Ok(properties)
+    }
+
+    fn find_tags(conn: &rusqlite::Connection, page_id: i32) -> rusqlite::Result<Vec<String>> {
+        let query = sql::Select::new()
+            .select("tag_name")
+            .from("page_tags")
+            .where_clause("page_id = ?1")
+            .order_by("tag_name ASC");
+        let mut stmt = conn.prepare_cached(&query.as_string())?;
+        let tags = stmt
+            .query_map([page_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(tags)
+    }
+
+    /// Create a page and return the new page UUID.
     pub async fn add(
-        client: &Client,
+        pool: &Pool,
+        actor: impl Into<Actor>,
         page_name: String,
         page_content: String,
+        page_tags: Vec<String>,
+        page_properties: PageProperties,
         uploads: Vec<PageUpload>,
-    ) -> Result<(), ServerError> {
+    ) -> Result<Uuid, ServerError> {
         let query = sql::Insert::new()
             .insert_into(
-                "pages (created_at, updated_at, page_uuid, page_name, page_content, page_text, page_toc)",
+                "pages (created_at, updated_at, page_uuid, page_name, page_content, page_text, page_toc, created_by, updated_by)",
             )
-            .values("(?1, ?2, ?3, ?4, ?5, ?6, ?7)");
+            .values("(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)");
 
         let now = UtcDateTime::now();
         let created_at = now.format(&Rfc3339)?;
         let updated_at = now.format(&Rfc3339)?;
         let page_uuid = Uuid::new_v4();
-        let (page_content, page_text, page_toc) = {
+        let (page_content, page_text, page_toc, page_links) = {
             let page_content = sanitize_html(&page_content);
-            let (document, toc) = transform_page(&page_content)?;
+            let (document, toc) = transform_page_in(&page_content, Some(&page_name))?;
             let page_text = html_to_text(&document);
             // let toc = generate_toc(&document);
-            (stringify_doc(&document)?, page_text, toc)
+            (
+                stringify_doc(&document)?,
+                page_text,
+                toc,
+                wiki_links(&document),
+            )
         };
-        match client
+        let uploads = Self::extract_uploads(uploads).await;
+        let actor = actor.into();
+        let user_id = actor.user_id;
+        match pool
             .conn_mut(move |conn| {
                 let tx = conn.transaction()?;
                 tx.execute(
@@ -113,57 +496,36 @@ impl PageEntity {
                         created_at.clone(),
                         updated_at.clone(),
                         page_uuid.to_string(),
-                        page_name,
+                        &page_name,
                         page_content,
                         page_text,
-                        page_toc
+                        page_toc,
+                        user_id,
                     ),
                 )?;
-
                 let page_id = tx.last_insert_rowid();
-                for upload in uploads {
-                    let file_uuid = Uuid::new_v4();
-
-                    let query = sql::Insert::new()
-                        .insert_into(
-                            "files (created_at, updated_at, file_uuid, file_name, file_size, content_type, file_content)",
-                        )
-                        .values("(?1, ?2, ?3, ?4, ?5, ?6, ?7)");
-
-                    tx.execute(
-                        &query.as_string(),
-                        (
-                            created_at.clone(),
-                            updated_at.clone(),
-                            file_uuid.to_string(),
-                            upload.0,
-                            upload.2.len(),
-                            upload.1,
-                            upload.2.to_vec(),
-                        ),
-                    )?;
+                AuditEntity::record(
+                    &tx,
+                    &actor,
+                    AuditAction::PageCreate,
+                    &page_uuid.to_string(),
+                    Some(&page_name),
+                )?;
 
-                    let file_id = tx.last_insert_rowid();
-                    let query = sql::Insert::new()
-                        .insert_into(
-                            "page_files (page_id, file_id)",
-                        )
-                        .values("(?1, ?2)");
-                    tx.execute(
-                        &query.as_string(),
-                        (
-                            page_id,
-                            file_id,
-                        ),
-                    )?;
+                if let Some(user_id) = user_id {
+                    Self::add_contributor(&tx, page_id, user_id, &created_at)?;
                 }
+                Self::save_links(&tx, page_id, &page_links)?;
+                Self::save_tags(&tx, page_id, &page_tags)?;
+                Self::save_properties(&tx, page_id, &page_properties)?;
+                Self::add_uploads(&tx, &actor, page_id, page_uuid, uploads, &created_at)?;
 
                 tx.commit()?;
                 Ok(())
             })
             .await
         {
-            Ok(_) => Ok(()),
+            Ok(_) => Ok(page_uuid),
             Err(e) => match e {
                 Rusqlite(rusqlite::Error::SqliteFailure(err, _)) => {
                     if err.code == rusqlite::ErrorCode::ConstraintViolation {
@@ -177,78 +539,153 @@ impl PageEntity {
         }
     }
 
+    /// Update a page and return the previous page name, the tags and
+    /// properties are kept when `None`.
+    #[allow(clippy::too_many_arguments)]
     pub async fn edit(
-        client: &Client,
+        pool: &Pool,
+        actor: impl Into<Actor>,
         page_uuid: Uuid,
         page_name: String,
         page_content: String,
+        page_tags: Option<Vec<String>>,
+        page_properties: Option<PageProperties>,
         uploads: Vec<PageUpload>,
-    ) -> Result<(), ServerError> {
+    ) -> Result<String, ServerError> {
+        let select = sql::Select::new()
+            .select("page_id, page_name")
+            .from("pages")
+            .where_clause("page_uuid = ?1");
+
         let query = sql::Update::new()
             .update("pages")
             .set(
-                "updated_at = ?1, page_name = ?2, page_content = ?3, page_text = ?4, page_toc = ?5",
+                "updated_at = ?1, page_name = ?2, page_content = ?3, page_text = ?4, page_toc = ?5, updated_by = ?6",
             )
-            .where_clause("page_uuid = ?6");
+            .where_clause("page_uuid = ?7");
 
         let now = UtcDateTime::now();
         let updated_at = now.format(&Rfc3339)?;
 
-        let (page_content, page_text, page_toc) = {
+        let (page_content, page_text, page_toc, page_links) = {
             let page_content = sanitize_html(&page_content);
-            let (document, toc) = transform_page(&page_content)?;
+            let (document, toc) = transform_page_in(&page_content, Some(&page_name))?;
             let page_text = html_to_text(&document);
-            (stringify_doc(&document)?, page_text, toc)
+            (
+                stringify_doc(&document)?,
+                page_text,
+                toc,
+                wiki_links(&document),
+            )
         };
+        let uploads = Self::extract_uploads(uploads).await;
+        let actor = actor.into();
+        let user_id = actor.user_id;
 
-        client
-            .conn(move |conn| {
-                let mut stmt = conn.prepare_cached(&query.as_string())?;
-                stmt.execute((
-                    updated_at,
-                    page_name,
-                    page_content,
-                    page_text,
-                    page_toc,
-                    page_uuid.to_string(),
-                ))?;
-                Ok(())
+        let content: Result<String, async_sqlite::Error> = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let (page_id, previous_name): (i64, String) = tx
+                    .prepare_cached(&select.as_string())?
+                    .query_row([page_uuid.to_string()], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })?;
+                tx.execute(
+                    &query.as_string(),
+                    (
+                        updated_at.clone(),
+                        &page_name,
+                        page_content,
+                        page_text,
+                        page_toc,
+                        user_id,
+                        page_uuid.to_string(),
+                    ),
+                )?;
+                AuditEntity::record_at(
+                    &tx,
+                    &actor,
+                    AuditAction::PageUpdate,
+                    &page_uuid.to_string(),
+                    Some(&page_name),
+                    &updated_at,
+                )?;
+                if previous_name != page_name {
+                    AuditEntity::record_at(
+                        &tx,
+                        &actor,
+                        AuditAction::PageRename,
+                        &page_uuid.to_string(),
+                        Some(&format!("{} -> {}", previous_name, page_name)),
+                        &updated_at,
+                    )?;
+                }
+                if let Some(user_id) = user_id {
+                    Self::add_contributor(&tx, page_id, user_id, &updated_at)?;
+                }
+                Self::save_links(&tx, page_id, &page_links)?;
+                if let Some(page_tags) = &page_tags {
+                    Self::save_tags(&tx, page_id, page_tags)?;
+                }
+                if let Some(page_properties) = &page_properties {
+                    Self::save_properties(&tx, page_id, page_properties)?;
+                }
+                Self::add_uploads(&tx, &actor, page_id, page_uuid, uploads, &updated_at)?;
+                tx.commit()?;
+                Ok(previous_name)
             })
-            .await?;
+            .await;
 
-        Ok(())
+        match content {
+            Ok(previous_name) => Ok(previous_name),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(Rusqlite(rusqlite::Error::SqliteFailure(err, _)))
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Err(ServerError::Conflict)
+            }
+            Err(e) => Err(e.into()),
+        }
     }
 
     pub async fn find_by_name(
-        client: &Client,
+        pool: &Pool,
         page_name: String,
         options: PageSelectOptions,
+        access: Access,
     ) -> Result<Self, ServerError> {
-        let query = sql::Select::new()
-            .select(
-                "page_id, created_at, updated_at, page_uuid, page_name, page_content, page_text, page_toc",
-            )
+        let mut query = Self::select().where_clause("pages.page_name = ?1");
+        let mut ancestor = sql::Select::new()
+            .select("pages.page_uuid")
             .from("pages")
-            .where_clause("page_name = ?1");
+            .where_clause("pages.page_name = ?1");
+        if let Some(condition) = access.read_condition("pages") {
+            query = query.where_clause(&condition);
+            ancestor = ancestor.where_clause(&condition);
+        }
 
-        let content: Result<PageEntity, async_sqlite::Error> = client
+        let content: Result<PageEntity, async_sqlite::Error> = pool
             .conn(move |conn| {
                 let mut stmt = conn.prepare_cached(&query.as_string())?;
-                stmt.query_row([page_name], |row| {
-                    let page_uuid = row.get::<_, String>("page_uuid")?;
-                    let page_uuid = page_uuid.parse().unwrap();
-                    Ok(PageEntity {
-                        page_id: row.get("page_id")?,
-                        created_at: row.get("created_at")?,
-                        updated_at: row.get("updated_at")?,
+                let mut entity = stmt.query_row([page_name], Self::from_row)?;
+                entity.page_tags = Self::find_tags(conn, entity.page_id)?;
+                entity.page_properties = Self::find_properties(conn, entity.page_id)?;
+                // ancestors the caller cannot read are shown as missing
+                let mut stmt = conn.prepare_cached(&ancestor.as_string())?;
+                for page_name in page_ancestors(&entity.page_name) {
+                    let page_uuid = {
+                        let mut rows = stmt.query([&page_name])?;
+                        match rows.next()? {
+                            Some(row) => Some(row.get::<_, String>(0)?.parse().unwrap()),
+                            None => None,
+                        }
+                    };
+                    entity.breadcrumbs.push(Breadcrumb {
+                        page_name,
                         page_uuid,
-                        page_name: row.get("page_name")?,
-                        page_content: row.get("page_content")?,
-                        page_text: row.get("page_text")?,
-                        page_toc: row.get("page_toc")?,
-                        page_files: Vec::new(),
-                    })
-                })
+                    });
+                }
+                Ok(entity)
             })
             .await;
 
@@ -256,7 +693,7 @@ impl PageEntity {
             Ok(mut entity) => {
                 if options.include_files {
                     entity.page_files =
-                        FileEntity::find_all_by_page_id(client, entity.page_id).await?;
+                        FileEntity::find_all_by_page_id(pool, entity.page_id).await?;
                     Ok(entity)
                 } else {
                     Ok(entity)
@@ -267,36 +704,554 @@ impl PageEntity {
         }
     }
 
-    pub async fn find_recent(client: &Client) -> Result<Vec<Self>, ServerError> {
-        let sql = sql::Select::new()
-            .select("pages.*")
-            .from("pages")
-            .order_by("updated_at DESC LIMIT 10");
+    pub async fn find_recent(
+        pool: &Pool,
+        limit: u32,
+        access: Access,
+    ) -> Result<Vec<Self>, ServerError> {
+        let mut sql = Self::select().order_by(&format!("pages.updated_at DESC LIMIT {}", limit));
+        if let Some(condition) = access.read_condition("pages") {
+            sql = sql.where_clause(&condition);
+        }
         // .where_clause("pf.page_id = ?");
-        let pages = client
+        let pages = pool
             .conn(move |conn| {
                 let mut stmt = conn.prepare_cached(&sql.as_string())?;
                 let mut rows = stmt.query([])?;
                 let mut pages = Vec::new();
                 while let Some(row) = rows.next()? {
+                    pages.push(Self::from_row(row)?);
+                }
+                Ok(pages)
+            })
+            .await?;
+        Ok(pages)
+    }
+
+    /// Pages matching the listing options, one page of `limit` pages
+    /// at a time.
+    pub async fn find_all(
+        pool: &Pool,
+        options: PageListOptions,
+        access: Access,
+    ) -> Result<PageList, ServerError> {
+        let column = format!("pages.{}", options.sort.column());
+        let (direction, comparison) = if options.descending {
+            ("DESC", "<")
+        } else {
+            ("ASC", ">")
+        };
+        let mut columns =
+            "pages.page_id, pages.page_uuid, pages.page_name, pages.created_at, pages.updated_at"
+                .to_owned();
+        if options.include_preview {
+            columns.push_str(", pages.page_text");
+        }
+        let mut query = sql::Select::new().select(&columns).from("pages");
+        if let Some(condition) = access.read_condition("pages") {
+            query = query.where_clause(&condition);
+        }
+        let mut params = Vec::new();
+        for (prop_key, prop_value) in options.properties {
+            query = query.where_clause(&format!(
+                "pages.page_id IN (SELECT page_id FROM page_properties WHERE prop_key = ?{} AND prop_value = ?{})",
+                params.len() + 1,
+                params.len() + 2,
+            ));
+            params.push(prop_key);
+            params.push(prop_value);
+        }
+        if let Some(prefix) = options.prefix {
+            query = query.where_clause(&format!(
+                "substr(pages.page_name, 1, length(?{0})) = ?{0}",
+                params.len() + 1,
+            ));
+            params.push(prefix);
+        }
+        // the page id breaks ties between pages with the same value
+        if let Some((value, page_id)) = options.cursor {
+            query = query.where_clause(&format!(
+                "({column} {comparison} ?{0} OR ({column} = ?{0} AND pages.page_id {comparison} CAST(?{1} AS INTEGER)))",
+                params.len() + 1,
+                params.len() + 2,
+            ));
+            params.push(value);
+            params.push(page_id.to_string());
+        }
+        // one more page than the limit tells whether there is a next one
+        let query = query.order_by(&format!(
+            "{column} {direction}, pages.page_id {direction} LIMIT {}",
+            options.limit + 1
+        ));
+        let properties = sql::Select::new()
+            .select("page_id, prop_key, prop_value")
+            .from("page_properties")
+            .where_clause(&format!(
+                "page_id IN (SELECT page_id FROM ({}))",
+                query.as_string()
+            ));
+
+        let sort = options.sort;
+        let limit = options.limit;
+        let include_preview = options.include_preview;
+        let list = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&properties.as_string())?;
+                let mut rows = stmt.query(rusqlite::params_from_iter(&params))?;
+                let mut page_properties: HashMap<i32, PageProperties> = HashMap::new();
+                while let Some(row) = rows.next()? {
+                    page_properties
+                        .entry(row.get("page_id")?)
+                        .or_default()
+                        .insert(row.get("prop_key")?, row.get("prop_value")?);
+                }
+
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query(rusqlite::params_from_iter(&params))?;
+                let mut pages = Vec::new();
+                let mut next_cursor = None;
+                let mut last_page_id = 0;
+                while let Some(row) = rows.next()? {
+                    let page_id: i32 = row.get("page_id")?;
+                    if pages.len() == limit {
+                        let last: &PageListing = pages.last().unwrap();
+                        let value = match sort {
+                            PageSort::Name => &last.page_name,
+                            PageSort::Created => &last.created_at,
+                            PageSort::Updated => &last.updated_at,
+                        };
+                        next_cursor = Some(encode_cursor(value, last_page_id));
+                        break;
+                    }
                     let page_uuid = row.get::<_, String>("page_uuid")?;
-                    let page_uuid = page_uuid.parse().unwrap();
-                    let page_entity = PageEntity {
-                        page_id: row.get("page_id")?,
+                    let preview_text = if include_preview {
+                        let page_text: String = row.get("page_text")?;
+                        Some(trim_preview_text(&page_text).to_owned())
+                    } else {
+                        None
+                    };
+                    pages.push(PageListing {
+                        page_uuid: page_uuid.parse().unwrap(),
+                        page_name: row.get("page_name")?,
                         created_at: row.get("created_at")?,
                         updated_at: row.get("updated_at")?,
-                        page_uuid,
-                        page_name: row.get("page_name")?,
-                        page_content: row.get("page_content")?,
-                        page_text: row.get("page_text")?,
-                        page_toc: row.get("page_toc")?,
-                        page_files: Vec::new(),
+                        page_properties: page_properties.remove(&page_id).unwrap_or_default(),
+                        preview_text,
+                    });
+                    last_page_id = page_id;
+                }
+                Ok(PageList { pages, next_cursor })
+            })
+            .await?;
+        Ok(list)
+    }
+
+    /// Pages directly below a page by name, along with names that only
+    /// have pages further below them.
+    pub async fn find_children(
+        pool: &Pool,
+        page_name: String,
+        access: Access,
+    ) -> Result<Vec<PageChild>, ServerError> {
+        // names below `Team/` sort between `Team/` and `Team0`
+        let prefix = format!("{}/", page_name);
+        let end = format!("{}0", page_name);
+        let mut query = sql::Select::new()
+            .select("pages.page_uuid, pages.page_name, pages.updated_at")
+            .from("pages")
+            .where_clause("pages.page_name >= ?1")
+            .where_clause("pages.page_name < ?2");
+        if let Some(condition) = access.read_condition("pages") {
+            query = query.where_clause(&condition);
+        }
+
+        let children = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query((&prefix, &end))?;
+                let mut children: BTreeMap<String, PageChild> = BTreeMap::new();
+                while let Some(row) = rows.next()? {
+                    let name: String = row.get("page_name")?;
+                    let (child, is_child) = match name[prefix.len()..].split_once('/') {
+                        Some((child, _)) => (child, false),
+                        None => (&name[prefix.len()..], true),
                     };
-                    pages.push(page_entity);
+                    if child.is_empty() {
+                        continue;
+                    }
+                    let entry = children
+                        .entry(child.to_owned())
+                        .or_insert_with(|| PageChild {
+                            page_name: format!("{}{}", prefix, child),
+                            page_uuid: None,
+                            updated_at: None,
+                            descendant_count: 0,
+                        });
+                    if is_child {
+                        let page_uuid = row.get::<_, String>("page_uuid")?;
+                        entry.page_uuid = Some(page_uuid.parse().unwrap());
+                        entry.updated_at = Some(row.get("updated_at")?);
+                    } else {
+                        entry.descendant_count += 1;
+                    }
+                }
+                Ok(children.into_values().collect())
+            })
+            .await?;
+        Ok(children)
+    }
+
+    /// Pages with a tag, most recently updated first.
+    pub async fn find_by_tag(
+        pool: &Pool,
+        tag_name: String,
+        access: Access,
+    ) -> Result<Vec<Self>, ServerError> {
+        let mut sql = Self::select()
+            .inner_join("page_tags t ON t.page_id = pages.page_id")
+            .where_clause("t.tag_name = ?1")
+            .order_by("pages.updated_at DESC");
+        if let Some(condition) = access.read_condition("pages") {
+            sql = sql.where_clause(&condition);
+        }
+        let pages = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&sql.as_string())?;
+                let mut rows = stmt.query([tag_name])?;
+                let mut pages = Vec::new();
+                while let Some(row) = rows.next()? {
+                    pages.push(Self::from_row(row)?);
                 }
                 Ok(pages)
             })
             .await?;
         Ok(pages)
     }
+
+    /// Suggest pages for a partial name, names starting with the input
+    /// first then names containing or resembling it.
+    ///
+    /// Prefixes are looked up in the case-insensitive name index and other
+    /// matches in the trigram index of names, so only a bounded number of
+    /// pages is read whatever the size of the wiki.
+    pub async fn suggest(
+        pool: &Pool,
+        input: String,
+        limit: usize,
+        access: Access,
+    ) -> Result<Vec<PageSuggestion>, ServerError> {
+        let columns = "p.page_uuid, p.page_name, p.updated_at, \
+            (SELECT COUNT(*) FROM page_links l WHERE l.target_name = p.page_name) AS link_count";
+        let mut prefixed = sql::Select::new()
+            .select(columns)
+            .from("pages p")
+            .where_clause("p.page_name COLLATE NOCASE >= ?1")
+            .where_clause("p.page_name COLLATE NOCASE < ?1 || char(1114111)");
+        let mut similar = sql::Select::new()
+            .select(columns)
+            .from("page_names_fts")
+            .inner_join("pages p ON p.page_id = page_names_fts.rowid")
+            .where_clause("page_names_fts MATCH ?1");
+        if let Some(condition) = access.read_condition("p") {
+            prefixed = prefixed.where_clause(&condition);
+            similar = similar.where_clause(&condition);
+        }
+        let prefixed = prefixed.order_by(&format!(
+            "link_count DESC, p.updated_at DESC LIMIT {}",
+            limit
+        ));
+        let similar =
+            similar.order_by(&format!("page_names_fts.rank LIMIT {}", SUGGEST_CANDIDATES));
+
+        let input: String = input
+            .trim()
+            .to_lowercase()
+            .chars()
+            .take(SUGGEST_INPUT_LENGTH)
+            .collect();
+        // names sharing any trigram of the input, too short input has none
+        let chars: Vec<char> = input.chars().collect();
+        let mut trigrams: Vec<String> = chars
+            .windows(3)
+            .map(|w| format!("\"{}\"", String::from_iter(w).replace('"', "\"\"")))
+            .collect();
+        trigrams.sort();
+        trigrams.dedup();
+        let trigrams = (!trigrams.is_empty()).then(|| trigrams.join(" OR "));
+
+        let prefix = input.clone();
+        let mut candidates = pool
+            .conn(move |conn| {
+                let mut candidates = Vec::new();
+                let mut stmt = conn.prepare_cached(&prefixed.as_string())?;
+                let mut rows = stmt.query([prefix])?;
+                while let Some(row) = rows.next()? {
+                    candidates.push(PageSuggestion::from_row(row)?);
+                }
+                if let Some(trigrams) = trigrams {
+                    let mut stmt = conn.prepare_cached(&similar.as_string())?;
+                    let mut rows = stmt.query([trigrams])?;
+                    while let Some(row) = rows.next()? {
+                        candidates.push(PageSuggestion::from_row(row)?);
+                    }
+                }
+                Ok(candidates)
+            })
+            .await?;
+
+        let mut seen = HashSet::new();
+        candidates.retain_mut(|candidate| {
+            if !seen.insert(candidate.page_uuid) {
+                return false;
+            }
+            let name = candidate.page_name.to_lowercase();
+            candidate.rank = if name.starts_with(&input) {
+                0
+            } else if name.contains(&input) {
+                1
+            } else if trigram_similarity(&name, &input) >= SUGGEST_SIMILARITY {
+                2
+            } else {
+                return false;
+            };
+            true
+        });
+        candidates.sort_by(|a, b| {
+            a.rank
+                .cmp(&b.rank)
+                .then(b.link_count.cmp(&a.link_count))
+                .then(b.updated_at.cmp(&a.updated_at))
+        });
+        candidates.truncate(limit);
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PageEntity, PageListOptions, PageProperties, PageSelectOptions, PageSort};
+    use crate::{
+        entity::{acl::Access, audit::Actor},
+        helpers::decode_cursor,
+        migrations::test_pool,
+    };
+    use async_sqlite::Pool;
+
+    fn properties(pairs: &[(&str, &str)]) -> PageProperties {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    async fn add_page(pool: &Pool, page_name: &str, page_properties: PageProperties) {
+        PageEntity::add(
+            pool,
+            Actor::default(),
+            page_name.to_owned(),
+            "<p>notes</p>".to_owned(),
+            vec![],
+            page_properties,
+            vec![],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn page_properties(pool: &Pool, page_name: &str) -> PageProperties {
+        PageEntity::find_by_name(
+            pool,
+            page_name.to_owned(),
+            PageSelectOptions::default(),
+            Access::default(),
+        )
+        .await
+        .unwrap()
+        .page_properties
+    }
+
+    fn list_options(page_properties: PageProperties) -> PageListOptions {
+        PageListOptions {
+            sort: PageSort::Name,
+            descending: false,
+            prefix: None,
+            properties: page_properties,
+            cursor: None,
+            limit: 50,
+            include_preview: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn edits_keep_or_replace_properties() {
+        let pool = test_pool().await;
+        let stored = properties(&[("owner", "ops"), ("status", "draft")]);
+        add_page(&pool, "Runbook", stored.clone()).await;
+        assert_eq!(page_properties(&pool, "Runbook").await, stored);
+
+        let page_uuid = PageEntity::find_by_name(
+            &pool,
+            "Runbook".to_owned(),
+            PageSelectOptions::default(),
+            Access::default(),
+        )
+        .await
+        .unwrap()
+        .page_uuid;
+        let edit = |page_properties| {
+            PageEntity::edit(
+                &pool,
+                Actor::default(),
+                page_uuid,
+                "Runbook".to_owned(),
+                "<p>more notes</p>".to_owned(),
+                None,
+                page_properties,
+                vec![],
+            )
+        };
+
+        edit(None).await.unwrap();
+        assert_eq!(page_properties(&pool, "Runbook").await, stored);
+
+        let replaced = properties(&[("status", "published")]);
+        edit(Some(replaced.clone())).await.unwrap();
+        assert_eq!(page_properties(&pool, "Runbook").await, replaced);
+
+        edit(Some(PageProperties::new())).await.unwrap();
+        assert!(page_properties(&pool, "Runbook").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn lists_pages_with_all_property_values() {
+        let pool = test_pool().await;
+        add_page(
+            &pool,
+            "Alerts",
+            properties(&[("owner", "ops"), ("status", "draft")]),
+        )
+        .await;
+        add_page(
+            &pool,
+            "Backups",
+            properties(&[("owner", "ops"), ("status", "published")]),
+        )
+        .await;
+        add_page(&pool, "Onboarding", properties(&[("owner", "people")])).await;
+        add_page(&pool, "Scratch", PageProperties::new()).await;
+
+        let names = |filter: &[(&str, &str)]| {
+            let options = list_options(properties(filter));
+            let pool = pool.clone();
+            async move {
+                PageEntity::find_all(&pool, options, Access::default())
+                    .await
+                    .unwrap()
+                    .pages
+                    .into_iter()
+                    .map(|page| page.page_name)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            names(&[]).await,
+            ["Alerts", "Backups", "Onboarding", "Scratch"]
+        );
+        assert_eq!(names(&[("owner", "ops")]).await, ["Alerts", "Backups"]);
+        assert_eq!(
+            names(&[("owner", "ops"), ("status", "published")]).await,
+            ["Backups"]
+        );
+        assert!(
+            names(&[("owner", "people"), ("status", "draft")])
+                .await
+                .is_empty()
+        );
+        assert!(names(&[("team", "ops")]).await.is_empty());
+
+        // listed pages carry all their properties, not only the filtered ones
+        let list = PageEntity::find_all(
+            &pool,
+            list_options(properties(&[("status", "draft")])),
+            Access::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            list.pages[0].page_properties,
+            properties(&[("owner", "ops"), ("status", "draft")])
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_through_listings_without_gaps() {
+        let pool = test_pool().await;
+        for page_name in ["Delta", "Alpha", "Echo", "Charlie", "Bravo"] {
+            add_page(&pool, page_name, PageProperties::new()).await;
+        }
+        // pages updated at the same time are ordered by page id
+        pool.conn(|conn| {
+            conn.execute(
+                "UPDATE pages SET updated_at = '2025-10-01T10:00:00Z' WHERE page_name != 'Echo'",
+                [],
+            )
+        })
+        .await
+        .unwrap();
+
+        let page_through = |sort, descending| {
+            let pool = pool.clone();
+            async move {
+                let mut names = Vec::new();
+                let mut cursor = None;
+                loop {
+                    let options = PageListOptions {
+                        sort,
+                        descending,
+                        cursor,
+                        limit: 2,
+                        ..list_options(PageProperties::new())
+                    };
+                    let list = PageEntity::find_all(&pool, options, Access::default())
+                        .await
+                        .unwrap();
+                    assert!(list.pages.len() <= 2);
+                    names.extend(list.pages.into_iter().map(|page| page.page_name));
+                    match list.next_cursor {
+                        Some(next) => cursor = Some(decode_cursor(&next).unwrap()),
+                        None => return names,
+                    }
+                }
+            }
+        };
+
+        assert_eq!(
+            page_through(PageSort::Name, false).await,
+            ["Alpha", "Bravo", "Charlie", "Delta", "Echo"]
+        );
+        assert_eq!(
+            page_through(PageSort::Name, true).await,
+            ["Echo", "Delta", "Charlie", "Bravo", "Alpha"]
+        );
+        assert_eq!(
+            page_through(PageSort::Updated, false).await,
+            ["Delta", "Alpha", "Charlie", "Bravo", "Echo"]
+        );
+        assert_eq!(
+            page_through(PageSort::Updated, true).await,
+            ["Echo", "Bravo", "Charlie", "Alpha", "Delta"]
+        );
+
+        // a listing that ends exactly at the limit has no next page
+        let options = PageListOptions {
+            limit: 5,
+            ..list_options(PageProperties::new())
+        };
+        let list = PageEntity::find_all(&pool, options, Access::default())
+            .await
+            .unwrap();
+        assert_eq!(list.pages.len(), 5);
+        assert_eq!(list.next_cursor, None);
+    }
 }
@@ -0,0 +1,301 @@
+use crate::error::ServerError;
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+pub struct SavedSearchEntity {
+    pub search_id: i32,
+    pub created_at: String,
+    pub search_uuid: Uuid,
+    pub search_name: String,
+    pub search_query: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchResponse {
+    search_uuid: Uuid,
+    search_name: String,
+    search_query: String,
+    created_at: String,
+}
+
+impl From<SavedSearchEntity> for SavedSearchResponse {
+    fn from(value: SavedSearchEntity) -> Self {
+        Self {
+            search_uuid: value.search_uuid,
+            search_name: value.search_name,
+            search_query: value.search_query,
+            created_at: value.created_at,
+        }
+    }
+}
+
+const COLUMNS: &str = "search_id, created_at, search_uuid, search_name, search_query";
+
+impl SavedSearchEntity {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let search_uuid = row.get::<_, String>("search_uuid")?;
+        Ok(Self {
+            search_id: row.get("search_id")?,
+            created_at: row.get("created_at")?,
+            search_uuid: search_uuid.parse().unwrap(),
+            search_name: row.get("search_name")?,
+            search_query: row.get("search_query")?,
+        })
+    }
+
+    /// Save a query for a user, names are unique per user.
+    pub async fn create(
+        pool: &Pool,
+        user_id: i32,
+        search_name: String,
+        search_query: String,
+    ) -> Result<Self, ServerError> {
+        let query = sql::Insert::new()
+            .insert_into(
+                "saved_searches (created_at, search_uuid, search_name, search_query, user_id)",
+            )
+            .values("(?1, ?2, ?3, ?4, ?5)");
+
+        let created_at = UtcDateTime::now().format(&Rfc3339)?;
+        let search_uuid = Uuid::new_v4();
+        let entity = SavedSearchEntity {
+            search_id: 0,
+            created_at: created_at.clone(),
+            search_uuid,
+            search_name: search_name.clone(),
+            search_query: search_query.clone(),
+        };
+
+        match pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.execute((
+                    created_at,
+                    search_uuid.to_string(),
+                    search_name,
+                    search_query,
+                    user_id,
+                ))?;
+                Ok(conn.last_insert_rowid())
+            })
+            .await
+        {
+            Ok(search_id) => Ok(SavedSearchEntity {
+                search_id: search_id as i32,
+                ..entity
+            }),
+            Err(Rusqlite(rusqlite::Error::SqliteFailure(err, _)))
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Err(ServerError::Conflict)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn find_all_by_user_id(pool: &Pool, user_id: i32) -> Result<Vec<Self>, ServerError> {
+        let query = sql::Select::new()
+            .select(COLUMNS)
+            .from("saved_searches")
+            .where_clause("user_id = ?1")
+            .order_by("search_name ASC");
+        let searches = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([user_id])?;
+                let mut searches = Vec::new();
+                while let Some(row) = rows.next()? {
+                    searches.push(SavedSearchEntity::from_row(row)?);
+                }
+                Ok(searches)
+            })
+            .await?;
+        Ok(searches)
+    }
+
+    pub async fn find_by_name(
+        pool: &Pool,
+        user_id: i32,
+        search_name: String,
+    ) -> Result<Self, ServerError> {
+        let query = sql::Select::new()
+            .select(COLUMNS)
+            .from("saved_searches")
+            .where_clause("user_id = ?1")
+            .where_clause("search_name = ?2");
+        let content: Result<Self, async_sqlite::Error> = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.query_row((user_id, search_name), SavedSearchEntity::from_row)
+            })
+            .await;
+        match content {
+            Ok(entity) => Ok(entity),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn delete(pool: &Pool, user_id: i32, search_name: String) -> Result<(), ServerError> {
+        let query = sql::Delete::new()
+            .delete_from("saved_searches")
+            .where_clause("user_id = ?1")
+            .where_clause("search_name = ?2");
+
+        let affected = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.execute((user_id, search_name))
+            })
+            .await?;
+
+        if affected == 0 {
+            Err(ServerError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SavedSearchEntity;
+    use crate::{
+        auth::Identity,
+        entity::{
+            audit::Actor,
+            page::{PageEntity, PageProperties},
+            user::{Role, UserEntity, test_user},
+        },
+        error::ServerError,
+        migrations::test_pool,
+        routes::{
+            SavedSearchFeedQuery, SavedSearchQuery, api_run_saved_search, api_saved_search_feed,
+        },
+        server::test_state,
+    };
+    use axum::{
+        Extension,
+        extract::{Path, Query},
+        http::HeaderMap,
+        response::Response,
+    };
+    use axum_extra::extract::Query as RepeatedQuery;
+
+    fn identity(user: &UserEntity) -> Extension<Identity> {
+        Extension(Identity {
+            user: Some(user.clone()),
+            role: Some(Role::Editor),
+            ..Identity::default()
+        })
+    }
+
+    async fn body(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn saved_searches_belong_to_their_user() {
+        let pool = test_pool().await;
+        let state = test_state(pool.clone());
+        let alice = test_user(&pool, 1, "alice").await;
+        let bob = test_user(&pool, 2, "bob").await;
+        PageEntity::add(
+            &pool,
+            Actor::default(),
+            "Oncall".to_owned(),
+            "<p>pager rotation</p>".to_owned(),
+            vec![],
+            PageProperties::new(),
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        let create = |user_id, name: &str| {
+            SavedSearchEntity::create(&pool, user_id, name.to_owned(), "pager".to_owned())
+        };
+        create(alice.user_id, "pager").await.unwrap();
+        create(bob.user_id, "pager").await.unwrap();
+        create(alice.user_id, "alice only").await.unwrap();
+        assert!(matches!(
+            create(alice.user_id, "pager").await,
+            Err(ServerError::Conflict)
+        ));
+
+        let run = |user: &UserEntity, name: &str| {
+            api_run_saved_search(
+                Extension(state.clone()),
+                identity(user),
+                Path(name.to_owned()),
+                RepeatedQuery(SavedSearchQuery {
+                    limit: None,
+                    offset: None,
+                    cursor: None,
+                    tag: vec![],
+                    editor: None,
+                    since: None,
+                    until: None,
+                }),
+            )
+        };
+        let results = body(run(&alice, "pager").await.unwrap()).await;
+        assert!(results.contains("\"total\":1"));
+        assert!(matches!(
+            run(&bob, "alice only").await,
+            Err(ServerError::NotFound)
+        ));
+
+        // fetching the feed twice gives the same feed and stores nothing
+        let stored = || {
+            pool.conn(|conn| {
+                let mut stmt = conn.prepare("SELECT * FROM saved_searches ORDER BY search_id")?;
+                let columns = stmt.column_count();
+                let mut rows = stmt.query(())?;
+                let mut values = Vec::new();
+                while let Some(row) = rows.next()? {
+                    for column in 0..columns {
+                        values.push(format!("{:?}", row.get_ref(column)?));
+                    }
+                }
+                Ok(values)
+            })
+        };
+        let before = stored().await.unwrap();
+        let feed = || {
+            api_saved_search_feed(
+                Extension(state.clone()),
+                identity(&alice),
+                HeaderMap::new(),
+                Path("pager".to_owned()),
+                Query(SavedSearchFeedQuery { since: None }),
+            )
+        };
+        let first = body(feed().await.unwrap()).await;
+        assert!(first.contains("<title>Oncall</title>"));
+        assert_eq!(body(feed().await.unwrap()).await, first);
+        assert_eq!(stored().await.unwrap(), before);
+
+        SavedSearchEntity::delete(&pool, bob.user_id, "alice only".to_owned())
+            .await
+            .unwrap_err();
+        SavedSearchEntity::delete(&pool, alice.user_id, "pager".to_owned())
+            .await
+            .unwrap();
+        let remaining = SavedSearchEntity::find_all_by_user_id(&pool, alice.user_id)
+            .await
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(
+            SavedSearchEntity::find_by_name(&pool, bob.user_id, "pager".to_owned())
+                .await
+                .is_ok()
+        );
+    }
+}
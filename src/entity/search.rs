@@ -1,11 +1,110 @@
-use crate::{error::ServerError, helpers::trim_preview_text};
-use async_sqlite::Client;
+use crate::{
+    config::Tokenizer,
+    entity::{
+        acl::Access,
+        audit::{Actor, AuditAction, AuditEntity},
+    },
+    error::ServerError,
+    helpers::{MATCH_END, MATCH_START, is_valid_date, mark_matches, normalize_tag},
+    query::SearchTerms,
+};
+use async_sqlite::{
+    Pool,
+    rusqlite::{self, params_from_iter},
+};
 use sql_query_builder as sql;
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
 
 #[derive(Debug, serde::Deserialize)]
 pub struct SearchQuery {
     #[serde(rename = "q")]
     pub keywords: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    /// Cursor returned as `next` by the previous page, takes
+    /// precedence over `offset`.
+    pub cursor: Option<String>,
+    /// Tags that pages must have, may be repeated.
+    #[serde(default)]
+    pub tag: Vec<String>,
+    /// Login of the user who last edited the page.
+    pub editor: Option<String>,
+    /// Only pages updated at or after this date or time.
+    pub since: Option<String>,
+    /// Only pages updated before this date or time.
+    pub until: Option<String>,
+}
+
+impl SearchQuery {
+    fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// Reject `since` and `until` values that are neither dates nor
+    /// RFC3339 timestamps, SQLite would ignore them silently.
+    fn check_dates(&self) -> Result<(), ServerError> {
+        let dates = [&self.since, &self.until];
+        if dates.into_iter().flatten().any(|date| !is_valid_date(date)) {
+            return Err(ServerError::BadRequest);
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> Result<u32, ServerError> {
+        match &self.cursor {
+            Some(cursor) => cursor.parse().map_err(|_| ServerError::BadRequest),
+            None => Ok(self.offset.unwrap_or_default()),
+        }
+    }
+}
+
+/// Page of search results.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    pub results: Vec<SearchRecord>,
+    /// Number of results matching the query, a page is counted once
+    /// for its own match and once for each matching attachment.
+    pub total: u64,
+    /// Cursor for the next page, absent on the last page.
+    pub next: Option<String>,
+    pub facets: SearchFacets,
+}
+
+impl SearchResponse {
+    pub fn new(results: Vec<SearchEntity>, total: u64, offset: u32, facets: SearchFacets) -> Self {
+        let end = offset as u64 + results.len() as u64;
+        Self {
+            results: results.into_iter().map(SearchRecord::from).collect(),
+            total,
+            next: (end < total).then(|| end.to_string()),
+            facets,
+        }
+    }
+
+    fn empty(offset: u32) -> Self {
+        Self::new(vec![], 0, offset, SearchFacets::default())
+    }
+}
+
+/// Number of matching pages with a value.
+#[derive(Debug, serde::Serialize)]
+pub struct Facet {
+    pub value: String,
+    pub count: u64,
+}
+
+/// Matching pages counted by tag, last editor and month of the last
+/// update, to narrow the results.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct SearchFacets {
+    pub tags: Vec<Facet>,
+    pub editors: Vec<Facet>,
+    /// Months as `YYYY-MM`, most recent first.
+    pub months: Vec<Facet>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -13,7 +112,15 @@ pub struct SearchQuery {
 pub struct SearchRecord {
     pub row_id: i32,
     pub title: String,
-    pub body: String,
+    /// Title as HTML with matched terms in `<mark>`.
+    pub title_html: String,
+    /// Text around the matched terms as HTML with matches in `<mark>`.
+    pub snippet_html: String,
+    /// Attachment that matched, the title is the page it belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_uuid: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
 }
 
 impl From<SearchEntity> for SearchRecord {
@@ -21,80 +128,621 @@ impl From<SearchEntity> for SearchRecord {
         Self {
             row_id: value.row_id,
             title: value.title,
-            body: trim_preview_text(&value.body).to_owned(),
+            title_html: mark_matches(&value.title_highlight),
+            snippet_html: mark_matches(&value.snippet),
+            file_uuid: value.file_uuid,
+            file_name: value.file_name,
         }
     }
 }
 
-fn sanitize_token(t: &str) -> String {
-    t.replace('\'', "''")
+/// Full-text tables with their columns, content table and rowid column.
+const FTS_TABLES: [(&str, &str, &str, &str); 2] = [
+    ("pages_fts", "page_name, page_text", "pages", "page_id"),
+    ("files_fts", "file_name, file_text", "files", "file_id"),
+];
+
+/// Result of rebuilding the full-text index.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReindexReport {
+    pub tokenizer: String,
+    pub pages: u64,
+    pub files: u64,
+}
+
+/// Maximum number of values returned per facet.
+const FACET_LIMIT: u32 = 20;
+
+/// Pages matching `?1` in their text or `?2` in their attachments.
+const MATCHED_PAGES: &str = "(p.page_id IN (SELECT rowid FROM pages_fts WHERE pages_fts MATCH ?1) \
+     OR p.page_id IN (SELECT pf.page_id FROM files_fts \
+     INNER JOIN page_files pf ON pf.file_id = files_fts.rowid \
+     WHERE files_fts MATCH ?2))";
+
+/// Filters on the page `p`, with `?3` the editor login and `?4` and
+/// `?5` the update range, each ignored when NULL.
+const FILTERS: [&str; 3] = [
+    "(?3 IS NULL OR p.updated_by = (SELECT user_id FROM users WHERE user_login = ?3))",
+    "(?4 IS NULL OR julianday(p.updated_at) >= julianday(?4))",
+    "(?5 IS NULL OR julianday(p.updated_at) < julianday(?5))",
+];
+
+/// First parameter bound to the tags of `fts_search`.
+const FIRST_TAG_PARAM: usize = 6;
+
+/// Conditions on the page `p` requiring each tag then rejecting each
+/// excluded tag, bound from `?first` in the order of `tag_params`.
+fn tag_conditions(terms: &SearchTerms, first: usize) -> Vec<String> {
+    let required = terms.tags.iter().map(|_| "IN");
+    let excluded = terms.exclude_tags.iter().map(|_| "NOT IN");
+    required
+        .chain(excluded)
+        .zip(first..)
+        .map(|(operator, param)| {
+            format!(
+                "p.page_id {} (SELECT page_id FROM page_tags WHERE tag_name = ?{})",
+                operator, param
+            )
+        })
+        .collect()
+}
+
+/// Parameters bound to the conditions of `tag_conditions`.
+fn tag_params(terms: SearchTerms) -> impl Iterator<Item = Option<String>> {
+    terms.tags.into_iter().chain(terms.exclude_tags).map(Some)
+}
+
+/// Parse a query with additional tags, `None` when a tag is invalid
+/// so no page can have it. Invalid excluded tags are ignored.
+fn parse_terms(keywords: &str, tags: Vec<String>) -> Option<SearchTerms> {
+    let mut terms = SearchTerms::parse(keywords);
+    terms.tags.extend(tags);
+    terms.tags = terms
+        .tags
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .collect::<Option<Vec<_>>>()?;
+    terms.tags.sort();
+    terms.tags.dedup();
+    terms.exclude_tags = terms
+        .exclude_tags
+        .iter()
+        .filter_map(|tag| normalize_tag(tag))
+        .collect();
+    terms.exclude_tags.sort();
+    terms.exclude_tags.dedup();
+    Some(terms)
+}
+
+/// Weight of the page name column in the bm25 rank.
+const NAME_WEIGHT: f64 = 10.0;
+/// Weight of the page text column in the bm25 rank.
+const TEXT_WEIGHT: f64 = 1.0;
+
+/// Page matching a query, for feeds.
+pub struct PageMatch {
+    pub page_uuid: Uuid,
+    pub page_name: String,
+    pub updated_at: String,
+    pub updated_by_login: Option<String>,
+    pub summary: Option<String>,
 }
 
 pub struct SearchEntity {
     pub row_id: i32,
     pub title: String,
-    pub body: String,
+    pub title_highlight: String,
+    pub snippet: String,
+    pub file_uuid: Option<Uuid>,
+    pub file_name: Option<String>,
 }
 
 impl SearchEntity {
     pub async fn fts_search(
-        client: &Client,
+        pool: &Pool,
         query: SearchQuery,
-    ) -> Result<Vec<SearchEntity>, ServerError> {
-        let match_expr = Self::build_fts_match(&query.keywords, 20).unwrap_or_default();
-        if match_expr.is_empty() {
-            return Ok(vec![]);
+        access: Access,
+    ) -> Result<SearchResponse, ServerError> {
+        query.check_dates()?;
+        let limit = query.limit();
+        let offset = query.offset()?;
+        let Some(terms) = parse_terms(&query.keywords, query.tag) else {
+            return Ok(SearchResponse::empty(offset));
+        };
+        let page_expr = terms.to_fts("page_name");
+        let file_expr = terms.to_fts("file_name");
+        // without words to match the tags alone select the pages
+        let fts = page_expr.is_some();
+        if !fts && terms.tags.is_empty() {
+            return Ok(SearchResponse::empty(offset));
         }
+        let tag_conditions = tag_conditions(&terms, FIRST_TAG_PARAM);
+        let mut params = vec![page_expr, file_expr, query.editor, query.since, query.until];
+        params.extend(tag_params(terms));
 
-        let search_query = query
-            .keywords
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join(" OR ");
-
-        let query = sql::Select::new()
-            .select("rowid as row_id, page_name as title, page_text as body")
-            .from("pages_fts")
-            .where_clause("pages_fts MATCH ?1 LIMIT 50");
+        // matches in the page name rank above matches in the text
+        let page_columns = format!(
+            "pages_fts.rowid as row_id, pages_fts.page_name as title, \
+             highlight(pages_fts, 0, '{start}', '{end}') as title_highlight, \
+             snippet(pages_fts, 1, '{start}', '{end}', '…', 24) as snippet, \
+             bm25(pages_fts, {NAME_WEIGHT:.1}, {TEXT_WEIGHT:.1}) as score, \
+             NULL as file_uuid, NULL as file_name",
+            start = MATCH_START,
+            end = MATCH_END,
+        );
+        let file_columns = format!(
+            "p.page_id as row_id, p.page_name as title, p.page_name as title_highlight, \
+             snippet(files_fts, 1, '{start}', '{end}', '…', 24) as snippet, \
+             bm25(files_fts, {NAME_WEIGHT:.1}, {TEXT_WEIGHT:.1}) as score, \
+             f.file_uuid as file_uuid, f.file_name as file_name",
+            start = MATCH_START,
+            end = MATCH_END,
+        );
+        let tagged_columns = "p.page_id as row_id, p.page_name as title, \
+             p.page_name as title_highlight, substr(p.page_text, 1, 200) as snippet, \
+             NULL as file_uuid, NULL as file_name";
+        let filtered = |mut query: sql::Select| {
+            if let Some(condition) = access.read_condition("p") {
+                query = query.where_clause(&condition);
+            }
+            FILTERS
+                .iter()
+                .copied()
+                .chain(tag_conditions.iter().map(String::as_str))
+                .fold(query, |query, condition| query.where_clause(condition))
+        };
+        let matching_pages = |columns: &str| {
+            filtered(
+                sql::Select::new()
+                    .select(columns)
+                    .from("pages_fts")
+                    .inner_join("pages p ON p.page_id = pages_fts.rowid"),
+            )
+            .where_clause("pages_fts MATCH ?1")
+        };
+        // attachments are found through the page they belong to
+        let matching_files = |columns: &str| {
+            filtered(
+                sql::Select::new()
+                    .select(columns)
+                    .from("files_fts")
+                    .inner_join("files f ON f.file_id = files_fts.rowid")
+                    .inner_join("page_files pf ON pf.file_id = f.file_id")
+                    .inner_join("pages p ON p.page_id = pf.page_id"),
+            )
+            .where_clause("files_fts MATCH ?2")
+        };
+        let tagged_pages =
+            |columns: &str| filtered(sql::Select::new().select(columns).from("pages p"));
+        // facets count each matching page once
+        let facet = |column: &str, join: Option<&str>, order: &str| {
+            let mut query = sql::Select::new()
+                .select(&format!("{} as value, COUNT(*) as count", column))
+                .from("pages p");
+            if let Some(join) = join {
+                query = query.inner_join(join);
+            }
+            let mut query = filtered(query);
+            if fts {
+                query = query.where_clause(MATCHED_PAGES);
+            }
+            query
+                .group_by("value")
+                .order_by(&format!("{} LIMIT {}", order, FACET_LIMIT))
+                .as_string()
+        };
+        let tags_facet = facet(
+            "t.tag_name",
+            Some("page_tags t ON t.page_id = p.page_id"),
+            "count DESC, value ASC",
+        );
+        let editors_facet = facet(
+            "u.user_login",
+            Some("users u ON u.user_id = p.updated_by"),
+            "count DESC, value ASC",
+        );
+        let months_facet = facet("substr(p.updated_at, 1, 7)", None, "value DESC");
+        let (count, query) = if fts {
+            let count = format!(
+                "SELECT ({}) + ({})",
+                matching_pages("COUNT(*)").as_string(),
+                matching_files("COUNT(*)").as_string(),
+            );
+            // equal scores are ordered by page and then attachment so
+            // that consecutive pages neither skip nor repeat results
+            let query = format!(
+                "{} UNION ALL {} ORDER BY score, row_id, file_uuid LIMIT {} OFFSET {}",
+                matching_pages(&page_columns).as_string(),
+                matching_files(&file_columns).as_string(),
+                limit,
+                offset,
+            );
+            (count, query)
+        } else {
+            let count = tagged_pages("COUNT(*)").as_string();
+            let query = tagged_pages(tagged_columns)
+                .order_by(&format!(
+                    "p.updated_at DESC, p.page_id DESC LIMIT {} OFFSET {}",
+                    limit, offset
+                ))
+                .as_string();
+            (count, query)
+        };
 
-        let results = client
+        let (results, total, facets) = pool
             .conn(move |conn| {
-                let mut stmt = conn.prepare_cached(&query.as_string())?;
-                let mut rows = stmt.query([search_query])?;
+                let total: u64 = conn
+                    .prepare_cached(&count)?
+                    .query_row(params_from_iter(&params), |row| row.get(0))?;
+                let mut stmt = conn.prepare_cached(&query)?;
+                let mut rows = stmt.query(params_from_iter(&params))?;
                 let mut results = Vec::new();
                 while let Some(row) = rows.next()? {
+                    let file_uuid: Option<String> = row.get("file_uuid")?;
                     let search_entity = SearchEntity {
                         row_id: row.get("row_id")?,
                         title: row.get("title")?,
-                        body: row.get("body")?,
+                        title_highlight: row.get("title_highlight")?,
+                        snippet: row.get("snippet")?,
+                        file_uuid: file_uuid.map(|file_uuid| file_uuid.parse().unwrap()),
+                        file_name: row.get("file_name")?,
                     };
                     results.push(search_entity);
                 }
-                Ok(results)
+
+                let facet_values = |facet: &str| {
+                    let mut stmt = conn.prepare_cached(facet)?;
+                    let mut rows = stmt.query(params_from_iter(&params))?;
+                    let mut values = Vec::new();
+                    while let Some(row) = rows.next()? {
+                        values.push(Facet {
+                            value: row.get("value")?,
+                            count: row.get("count")?,
+                        });
+                    }
+                    Ok::<_, rusqlite::Error>(values)
+                };
+                let facets = SearchFacets {
+                    tags: facet_values(&tags_facet)?,
+                    editors: facet_values(&editors_facet)?,
+                    months: facet_values(&months_facet)?,
+                };
+                Ok((results, total, facets))
             })
             .await?;
-        Ok(results)
+        Ok(SearchResponse::new(results, total, offset, facets))
     }
 
-    fn build_fts_match(input: &str, max_tokens: usize) -> Option<String> {
-        let tokens: Vec<_> = input
-            .split_whitespace()
-            .map(sanitize_token)
-            .filter(|s| !s.is_empty())
-            .take(max_tokens)
-            .collect();
+    /// Most recently updated pages matching a query in their text or
+    /// attachments, only pages updated after `since` when given.
+    pub async fn find_matching_pages(
+        pool: &Pool,
+        keywords: &str,
+        access: Access,
+        since: Option<String>,
+        limit: u32,
+    ) -> Result<Vec<PageMatch>, ServerError> {
+        let Some(terms) = parse_terms(keywords, Vec::new()) else {
+            return Ok(vec![]);
+        };
+        let page_expr = terms.to_fts("page_name");
+        let file_expr = terms.to_fts("file_name");
+        let fts = page_expr.is_some();
+        if !fts && terms.tags.is_empty() {
+            return Ok(vec![]);
+        }
 
-        if tokens.is_empty() {
-            None
-        } else {
-            // wrap each token in single quotes to force term match and join with OR
-            Some(
-                tokens
-                    .into_iter()
-                    // .map(|t| format!("'{}'", t))
-                    .collect::<Vec<_>>()
-                    .join(" OR "),
+        let mut query = sql::Select::new()
+            .select(
+                "p.page_uuid, p.page_name, p.updated_at, uu.user_login as updated_by_login, \
+                 substr(p.page_text, 1, 280) as summary",
             )
+            .from("pages p")
+            .left_join("users uu ON uu.user_id = p.updated_by")
+            .where_clause("(?3 IS NULL OR julianday(p.updated_at) > julianday(?3))");
+        if fts {
+            query = query.where_clause(MATCHED_PAGES);
+        }
+        if let Some(condition) = access.read_condition("p") {
+            query = query.where_clause(&condition);
         }
+        for condition in tag_conditions(&terms, 4) {
+            query = query.where_clause(&condition);
+        }
+        let query = query.order_by(&format!("p.updated_at DESC LIMIT {}", limit));
+        let mut params = vec![page_expr, file_expr, since];
+        params.extend(tag_params(terms));
+
+        let pages = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query(params_from_iter(&params))?;
+                let mut pages = Vec::new();
+                while let Some(row) = rows.next()? {
+                    let page_uuid = row.get::<_, String>("page_uuid")?;
+                    pages.push(PageMatch {
+                        page_uuid: page_uuid.parse().unwrap(),
+                        page_name: row.get("page_name")?,
+                        updated_at: row.get("updated_at")?,
+                        updated_by_login: row.get("updated_by_login")?,
+                        summary: row.get("summary")?,
+                    });
+                }
+                Ok(pages)
+            })
+            .await?;
+        Ok(pages)
+    }
+
+    /// Tokenizer arguments the full-text index was built with.
+    pub async fn current_tokenizer(pool: &Pool) -> Result<Option<String>, ServerError> {
+        let query = sql::Select::new()
+            .select("sql")
+            .from("sqlite_master")
+            .where_clause("name = 'pages_fts'");
+        let schema: Option<String> = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([])?;
+                match rows.next()? {
+                    Some(row) => row.get(0),
+                    None => Ok(None),
+                }
+            })
+            .await?;
+        Ok(schema.and_then(|schema| {
+            let (_, options) = schema.split_once("tokenize")?;
+            let (_, quoted) = options.split_once('\'')?;
+            let (tokenizer, _) = quoted.split_once('\'')?;
+            Some(tokenizer.to_owned())
+        }))
+    }
+
+    /// Recreate the full-text tables with a tokenizer, rebuild them
+    /// from their content tables, verify and optimize them.
+    pub async fn reindex(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        tokenizer: Tokenizer,
+    ) -> Result<ReindexReport, ServerError> {
+        let tokenize = tokenizer.as_sql();
+        let actor = actor.into();
+        let (pages, files) = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                for (table, columns, content, rowid) in FTS_TABLES {
+                    tx.execute_batch(&format!(
+                        "DROP TABLE IF EXISTS {table};
+                         CREATE VIRTUAL TABLE {table} USING fts5(
+                             {columns},
+                             content='{content}',
+                             content_rowid='{rowid}',
+                             tokenize = '{tokenize}'
+                         );
+                         INSERT INTO {table}({table}) VALUES('rebuild');
+                         INSERT INTO {table}({table}, rank) VALUES('integrity-check', 1);
+                         INSERT INTO {table}({table}) VALUES('optimize');"
+                    ))?;
+                }
+                let pages: u64 =
+                    tx.query_row("SELECT COUNT(*) FROM pages", [], |row| row.get(0))?;
+                let files: u64 = tx.query_row(
+                    "SELECT COUNT(*) FROM files WHERE file_text IS NOT NULL",
+                    [],
+                    |row| row.get(0),
+                )?;
+                AuditEntity::record(
+                    &tx,
+                    &actor,
+                    AuditAction::SearchReindex,
+                    "pages_fts",
+                    Some(tokenize),
+                )?;
+                tx.commit()?;
+                Ok((pages, files))
+            })
+            .await?;
+        Ok(ReindexReport {
+            tokenizer: tokenize.to_owned(),
+            pages,
+            files,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Facet, SearchEntity, SearchQuery};
+    use crate::{
+        entity::{
+            acl::Access,
+            audit::Actor,
+            page::{PageEntity, PageProperties, PageUpload},
+            user::test_user,
+        },
+        error::ServerError,
+        migrations::test_pool,
+    };
+    use axum::body::Bytes;
+
+    fn query(since: Option<&str>, editor: Option<&str>) -> SearchQuery {
+        SearchQuery {
+            keywords: "pager".to_owned(),
+            limit: None,
+            offset: None,
+            cursor: None,
+            tag: vec![],
+            editor: editor.map(str::to_owned),
+            since: since.map(str::to_owned),
+            until: None,
+        }
+    }
+
+    fn counts(facets: &[Facet]) -> Vec<(&str, u64)> {
+        facets
+            .iter()
+            .map(|facet| (facet.value.as_str(), facet.count))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn counts_results_and_facets() {
+        let pool = test_pool().await;
+        let alice = test_user(&pool, 1, "alice").await;
+        let bob = test_user(&pool, 2, "bob").await;
+        let pages = [
+            (
+                &alice,
+                "Oncall",
+                "pager rotation",
+                "ops",
+                "2025-09-15T10:00:00Z",
+            ),
+            (
+                &bob,
+                "Runbook",
+                "pager escalation",
+                "ops,db",
+                "2025-10-02T10:00:00Z",
+            ),
+            (&alice, "Lunch", "menu", "food", "2025-10-03T10:00:00Z"),
+        ];
+        for (user, page_name, text, tags, updated_at) in pages {
+            // the attachment of `Oncall` matches as well as its text
+            let uploads = match page_name {
+                "Oncall" => vec![PageUpload(
+                    "pager.txt".to_owned(),
+                    "text/plain".to_owned(),
+                    Bytes::from_static(b"pager duty"),
+                )],
+                _ => vec![],
+            };
+            PageEntity::add(
+                &pool,
+                Actor::new(user, None),
+                page_name.to_owned(),
+                format!("<p>{}</p>", text),
+                tags.split(',').map(str::to_owned).collect(),
+                PageProperties::new(),
+                uploads,
+            )
+            .await
+            .unwrap();
+            let page_name = page_name.to_owned();
+            let updated_at = updated_at.to_owned();
+            pool.conn(move |conn| {
+                conn.execute(
+                    "UPDATE pages SET updated_at = ?1 WHERE page_name = ?2",
+                    (updated_at, page_name),
+                )
+            })
+            .await
+            .unwrap();
+        }
+
+        let search = |query| SearchEntity::fts_search(&pool, query, Access::default());
+        let response = search(query(None, None)).await.unwrap();
+        // two pages and one attachment
+        assert_eq!(response.total, 3);
+        assert_eq!(response.results.len(), 3);
+        assert_eq!(response.next, None);
+        assert_eq!(counts(&response.facets.tags), [("ops", 2), ("db", 1)]);
+        assert_eq!(counts(&response.facets.editors), [("alice", 1), ("bob", 1)]);
+        assert_eq!(
+            counts(&response.facets.months),
+            [("2025-10", 1), ("2025-09", 1)]
+        );
+
+        let response = search(query(Some("2025-10-01"), None)).await.unwrap();
+        assert_eq!(response.total, 1);
+        assert_eq!(response.results[0].title, "Runbook");
+
+        let response = search(query(None, Some("alice"))).await.unwrap();
+        assert_eq!(response.total, 2);
+        assert_eq!(counts(&response.facets.editors), [("alice", 1)]);
+
+        assert!(matches!(
+            search(query(Some("last week"), None)).await,
+            Err(ServerError::BadRequest)
+        ));
+    }
+
+    #[tokio::test]
+    async fn pages_through_results_without_gaps() {
+        let pool = test_pool().await;
+        for n in 1..=5 {
+            // every page matches equally, only the tie breakers order them
+            let uploads = match n {
+                3 => vec![PageUpload(
+                    "pager.txt".to_owned(),
+                    "text/plain".to_owned(),
+                    Bytes::from_static(b"pager duty"),
+                )],
+                _ => vec![],
+            };
+            PageEntity::add(
+                &pool,
+                Actor::default(),
+                format!("Rotation {}", n),
+                "<p>pager notes</p>".to_owned(),
+                vec!["ops".to_owned()],
+                PageProperties::new(),
+                uploads,
+            )
+            .await
+            .unwrap();
+        }
+
+        let page_through = |keywords: &str, tag: Vec<String>| {
+            let pool = pool.clone();
+            let keywords = keywords.to_owned();
+            async move {
+                let mut results = Vec::new();
+                let mut cursor = None;
+                loop {
+                    let query = SearchQuery {
+                        keywords: keywords.clone(),
+                        limit: Some(2),
+                        offset: None,
+                        cursor,
+                        tag: tag.clone(),
+                        editor: None,
+                        since: None,
+                        until: None,
+                    };
+                    let response = SearchEntity::fts_search(&pool, query, Access::default())
+                        .await
+                        .unwrap();
+                    assert!(response.results.len() <= 2);
+                    results.extend(
+                        response
+                            .results
+                            .into_iter()
+                            .map(|result| (result.title, result.file_name)),
+                    );
+                    match response.next {
+                        Some(next) => cursor = Some(next),
+                        None => return (results, response.total),
+                    }
+                }
+            }
+        };
+
+        let (results, total) = page_through("pager", vec![]).await;
+        assert_eq!(total, 6);
+        let mut expected: Vec<_> = (1..=5).map(|n| (format!("Rotation {}", n), None)).collect();
+        expected.push(("Rotation 3".to_owned(), Some("pager.txt".to_owned())));
+        let mut found = results;
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let (results, total) = page_through("", vec!["ops".to_owned()]).await;
+        assert_eq!(total, 5);
+        let mut found: Vec<_> = results.into_iter().map(|(title, _)| title).collect();
+        found.sort();
+        found.dedup();
+        assert_eq!(found.len(), 5);
     }
 }
@@ -0,0 +1,128 @@
+use crate::{
+    entity::{
+        audit::{Actor, AuditAction, AuditEntity},
+        user::UserEntity,
+    },
+    error::ServerError,
+    helpers::{generate_token, hash_token},
+};
+use async_sqlite::{
+    Error::Rusqlite,
+    Pool,
+    rusqlite::{self, OptionalExtension},
+};
+use sql_query_builder as sql;
+use time::{Duration, UtcDateTime, format_description::well_known::Rfc3339};
+
+/// Lifetime of a login session.
+pub const SESSION_LIFETIME: Duration = Duration::days(30);
+
+pub struct SessionEntity;
+
+impl SessionEntity {
+    /// Create a session for a user and return the session token.
+    ///
+    /// Only a hash of the token is stored.
+    pub async fn create(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        user: &UserEntity,
+    ) -> Result<String, ServerError> {
+        let purge = sql::Delete::new()
+            .delete_from("sessions")
+            .where_clause("expires_at <= ?1");
+
+        let insert = sql::Insert::new()
+            .insert_into("sessions (created_at, expires_at, session_hash, user_id)")
+            .values("(?1, ?2, ?3, ?4)");
+
+        let now = UtcDateTime::now();
+        let created_at = now.format(&Rfc3339)?;
+        let expires_at = (now + SESSION_LIFETIME).format(&Rfc3339)?;
+        let token = generate_token();
+        let session_hash = hash_token(&token);
+
+        let actor = actor.into();
+        let user_id = user.user_id;
+        let user_uuid = user.user_uuid;
+        pool.conn_mut(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(&purge.as_string(), [created_at.clone()])?;
+            tx.execute(
+                &insert.as_string(),
+                (created_at, expires_at, session_hash, user_id),
+            )?;
+            AuditEntity::record(
+                &tx,
+                &actor,
+                AuditAction::Login,
+                &user_uuid.to_string(),
+                None,
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await?;
+        Ok(token)
+    }
+
+    /// Find the user for an unexpired session token.
+    pub async fn find_user(pool: &Pool, token: &str) -> Result<Option<UserEntity>, ServerError> {
+        let query = sql::Select::new()
+            .select("u.*")
+            .from("sessions s")
+            .inner_join("users u ON s.user_id = u.user_id")
+            .where_clause("s.session_hash = ?1")
+            .where_clause("s.expires_at > ?2");
+
+        let now = UtcDateTime::now().format(&Rfc3339)?;
+        let session_hash = hash_token(token);
+        let content: Result<UserEntity, async_sqlite::Error> = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.query_row((session_hash, now), UserEntity::from_row)
+            })
+            .await;
+
+        match content {
+            Ok(entity) => Ok(Some(entity)),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Delete the session for a token, recording a logout when
+    /// the session existed.
+    pub async fn delete(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        token: &str,
+    ) -> Result<(), ServerError> {
+        let select = sql::Select::new()
+            .select("u.user_uuid")
+            .from("sessions s")
+            .inner_join("users u ON s.user_id = u.user_id")
+            .where_clause("s.session_hash = ?1");
+
+        let query = sql::Delete::new()
+            .delete_from("sessions")
+            .where_clause("session_hash = ?1");
+
+        let actor = actor.into();
+        let session_hash = hash_token(token);
+        pool.conn_mut(move |conn| {
+            let tx = conn.transaction()?;
+            let user_uuid: Option<String> = tx
+                .prepare_cached(&select.as_string())?
+                .query_row([&session_hash], |row| row.get(0))
+                .optional()?;
+            tx.execute(&query.as_string(), [&session_hash])?;
+            if let Some(user_uuid) = user_uuid {
+                AuditEntity::record(&tx, &actor, AuditAction::Logout, &user_uuid, None)?;
+            }
+            tx.commit()
+        })
+        .await?;
+        Ok(())
+    }
+}
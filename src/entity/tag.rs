@@ -0,0 +1,54 @@
+use crate::{entity::acl::Access, error::ServerError};
+use async_sqlite::Pool;
+use sql_query_builder as sql;
+
+pub struct TagEntity {
+    pub tag_name: String,
+    pub page_count: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagResponse {
+    tag_name: String,
+    page_count: i64,
+}
+
+impl From<TagEntity> for TagResponse {
+    fn from(value: TagEntity) -> Self {
+        Self {
+            tag_name: value.tag_name,
+            page_count: value.page_count,
+        }
+    }
+}
+
+impl TagEntity {
+    /// Tags with the number of pages the caller can read, most used first.
+    pub async fn find_all(pool: &Pool, access: Access) -> Result<Vec<Self>, ServerError> {
+        let mut query = sql::Select::new()
+            .select("t.tag_name, COUNT(*) as page_count")
+            .from("page_tags t")
+            .inner_join("pages p ON p.page_id = t.page_id")
+            .group_by("t.tag_name")
+            .order_by("page_count DESC, t.tag_name ASC");
+        if let Some(condition) = access.read_condition("p") {
+            query = query.where_clause(&condition);
+        }
+        let tags = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([])?;
+                let mut tags = Vec::new();
+                while let Some(row) = rows.next()? {
+                    tags.push(TagEntity {
+                        tag_name: row.get("tag_name")?,
+                        page_count: row.get("page_count")?,
+                    });
+                }
+                Ok(tags)
+            })
+            .await?;
+        Ok(tags)
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    entity::{
+        audit::{Actor, AuditAction, AuditEntity},
+        user::{Role, UserEntity},
+    },
+    error::ServerError,
+    helpers::{generate_token, hash_token},
+};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use time::{Duration, UtcDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+/// Prefix for API tokens so they are easy to recognise in secret scanners.
+const TOKEN_PREFIX: &str = "twl_";
+
+/// The last use of a token is recorded at most this often.
+const TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+/// Scope of an API token, caps the role of the owning user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Admin => "admin",
+        }
+    }
+
+    /// Highest role granted by this scope.
+    pub fn role(&self) -> Role {
+        match self {
+            Self::Read => Role::Reader,
+            Self::Write => Role::Editor,
+            Self::Admin => Role::Admin,
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "admin" => Self::Admin,
+            "write" => Self::Write,
+            _ => Self::Read,
+        }
+    }
+}
+
+/// User and scope of a token found by its secret.
+pub struct TokenOwner {
+    pub user: UserEntity,
+    pub token_scope: TokenScope,
+    pub token_id: i32,
+    /// The recorded last use is missing or older than `TOUCH_INTERVAL`.
+    pub stale: bool,
+}
+
+pub struct ApiTokenEntity {
+    pub token_id: i32,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub token_uuid: Uuid,
+    pub token_name: String,
+    pub token_scope: TokenScope,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenResponse {
+    token_uuid: Uuid,
+    token_name: String,
+    token_scope: TokenScope,
+    created_at: String,
+    last_used_at: Option<String>,
+    /// Secret value, only present when the token is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+impl From<ApiTokenEntity> for ApiTokenResponse {
+    fn from(value: ApiTokenEntity) -> Self {
+        Self {
+            token_uuid: value.token_uuid,
+            token_name: value.token_name,
+            token_scope: value.token_scope,
+            created_at: value.created_at,
+            last_used_at: value.last_used_at,
+            token: None,
+        }
+    }
+}
+
+impl ApiTokenEntity {
+    /// Create a token for a user.
+    ///
+    /// The secret is returned in the response and only a hash is stored.
+    pub async fn create(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        user: &UserEntity,
+        token_name: String,
+        token_scope: TokenScope,
+    ) -> Result<ApiTokenResponse, ServerError> {
+        let query = sql::Insert::new()
+            .insert_into(
+                "api_tokens (created_at, token_uuid, token_name, token_hash, token_scope, user_id)",
+            )
+            .values("(?1, ?2, ?3, ?4, ?5, ?6)");
+
+        let created_at = UtcDateTime::now().format(&Rfc3339)?;
+        let token_uuid = Uuid::new_v4();
+        let token = format!("{}{}", TOKEN_PREFIX, generate_token());
+        let token_hash = hash_token(&token);
+
+        let response = ApiTokenResponse {
+            token_uuid,
+            token_name: token_name.clone(),
+            token_scope,
+            created_at: created_at.clone(),
+            last_used_at: None,
+            token: Some(token),
+        };
+
+        let actor = actor.into();
+        let user_id = user.user_id;
+        let user_uuid = user.user_uuid;
+        pool.conn_mut(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                &query.as_string(),
+                (
+                    created_at,
+                    token_uuid.to_string(),
+                    &token_name,
+                    token_hash,
+                    token_scope.as_str(),
+                    user_id,
+                ),
+            )?;
+            AuditEntity::record(
+                &tx,
+                &actor,
+                AuditAction::TokenCreate,
+                &user_uuid.to_string(),
+                Some(&format!("{} ({})", token_name, token_scope.as_str())),
+            )?;
+            tx.commit()
+        })
+        .await?;
+        Ok(response)
+    }
+
+    pub async fn find_all_by_user_id(pool: &Pool, user_id: i32) -> Result<Vec<Self>, ServerError> {
+        let sql = sql::Select::new()
+            .select("token_id, created_at, last_used_at, token_uuid, token_name, token_scope")
+            .from("api_tokens")
+            .where_clause("user_id = ?1")
+            .order_by("created_at DESC");
+        let tokens = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&sql.as_string())?;
+                let mut rows = stmt.query([user_id])?;
+                let mut tokens = Vec::new();
+                while let Some(row) = rows.next()? {
+                    let token_uuid = row.get::<_, String>("token_uuid")?;
+                    let token_uuid = token_uuid.parse().unwrap();
+                    let token_scope = row.get::<_, String>("token_scope")?;
+                    tokens.push(ApiTokenEntity {
+                        token_id: row.get("token_id")?,
+                        created_at: row.get("created_at")?,
+                        last_used_at: row.get("last_used_at")?,
+                        token_uuid,
+                        token_name: row.get("token_name")?,
+                        token_scope: TokenScope::parse(&token_scope),
+                    });
+                }
+                Ok(tokens)
+            })
+            .await?;
+        Ok(tokens)
+    }
+
+    /// Find the user and scope for a token.
+    pub async fn find_user(pool: &Pool, token: &str) -> Result<Option<TokenOwner>, ServerError> {
+        let select = sql::Select::new()
+            .select(
+                "t.token_id, t.token_scope, \
+                t.last_used_at IS NULL OR julianday(t.last_used_at) < julianday(?2) AS stale, u.*",
+            )
+            .from("api_tokens t")
+            .inner_join("users u ON t.user_id = u.user_id")
+            .where_clause("t.token_hash = ?1");
+
+        let stale_before = (UtcDateTime::now() - TOUCH_INTERVAL).format(&Rfc3339)?;
+        let token_hash = hash_token(token);
+        let content: Result<TokenOwner, async_sqlite::Error> = pool
+            .conn(move |conn| {
+                conn.prepare_cached(&select.as_string())?.query_row(
+                    (token_hash, stale_before),
+                    |row| {
+                        let token_scope = row.get::<_, String>("token_scope")?;
+                        Ok(TokenOwner {
+                            user: UserEntity::from_row(row)?,
+                            token_scope: TokenScope::parse(&token_scope),
+                            token_id: row.get("token_id")?,
+                            stale: row.get("stale")?,
+                        })
+                    },
+                )
+            })
+            .await;
+
+        match content {
+            Ok(found) => Ok(Some(found)),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record a token as used now.
+    pub async fn touch(pool: &Pool, token_id: i32) -> Result<(), ServerError> {
+        let query = sql::Update::new()
+            .update("api_tokens")
+            .set("last_used_at = ?1")
+            .where_clause("token_id = ?2");
+
+        let now = UtcDateTime::now().format(&Rfc3339)?;
+        pool.conn(move |conn| {
+            let mut stmt = conn.prepare_cached(&query.as_string())?;
+            stmt.execute((now, token_id))?;
+            Ok(())
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        user: &UserEntity,
+        token_uuid: Uuid,
+    ) -> Result<(), ServerError> {
+        let query = sql::Delete::new()
+            .delete_from("api_tokens")
+            .where_clause("token_uuid = ?1")
+            .where_clause("user_id = ?2");
+
+        let actor = actor.into();
+        let user_id = user.user_id;
+        let user_uuid = user.user_uuid;
+        let affected = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let affected = tx.execute(&query.as_string(), (token_uuid.to_string(), user_id))?;
+                if affected > 0 {
+                    AuditEntity::record(
+                        &tx,
+                        &actor,
+                        AuditAction::TokenRevoke,
+                        &user_uuid.to_string(),
+                        Some(&token_uuid.to_string()),
+                    )?;
+                }
+                tx.commit()?;
+                Ok(affected)
+            })
+            .await?;
+
+        if affected == 0 {
+            Err(ServerError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ApiTokenEntity, TokenScope};
+    use crate::{
+        entity::{audit::Actor, user::test_user},
+        helpers::hash_token,
+        migrations::test_pool,
+    };
+
+    #[tokio::test]
+    async fn stores_hashes_and_forgets_revoked_tokens() {
+        let pool = test_pool().await;
+        let user = test_user(&pool, 1, "octocat").await;
+        let created = ApiTokenEntity::create(
+            &pool,
+            Actor::default(),
+            &user,
+            "ci".to_owned(),
+            TokenScope::Read,
+        )
+        .await
+        .unwrap();
+        let token = created.token.clone().unwrap();
+
+        let stored: String = pool
+            .conn(|conn| conn.query_row("SELECT token_hash FROM api_tokens", (), |row| row.get(0)))
+            .await
+            .unwrap();
+        assert_eq!(stored, hash_token(&token));
+        assert!(!stored.contains(&token));
+
+        let owner = ApiTokenEntity::find_user(&pool, &token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(owner.user.user_id, user.user_id);
+        assert_eq!(owner.token_scope, TokenScope::Read);
+        assert!(owner.stale);
+
+        ApiTokenEntity::delete(&pool, Actor::default(), &user, created.token_uuid)
+            .await
+            .unwrap();
+        assert!(
+            ApiTokenEntity::find_user(&pool, &token)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    config::Auth,
+    entity::audit::{Actor, AuditAction, AuditEntity},
+    error::ServerError,
+};
+use async_sqlite::{Error::Rusqlite, Pool, rusqlite};
+use sql_query_builder as sql;
+use std::{fmt, str::FromStr};
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+/// Role granted to a user, ordered by privilege.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// View pages and files.
+    Reader,
+    /// Create and edit pages.
+    #[default]
+    Editor,
+    /// Delete pages and manage users.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reader => "reader",
+            Self::Editor => "editor",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Self::Reader),
+            "editor" => Ok(Self::Editor),
+            "admin" => Ok(Self::Admin),
+            _ => Err(ServerError::BadRequest),
+        }
+    }
+}
+
+/// Profile returned by the GitHub user API.
+#[derive(Debug, serde::Deserialize)]
+pub struct GithubUser {
+    pub id: i64,
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// Sign up a user for tests, the first one is an admin.
+#[cfg(test)]
+pub(crate) async fn test_user(pool: &Pool, id: i64, login: &str) -> UserEntity {
+    let profile = GithubUser {
+        id,
+        login: login.to_owned(),
+        name: None,
+        avatar_url: None,
+    };
+    UserEntity::upsert_github(pool, profile, &Auth::default())
+        .await
+        .unwrap()
+}
+
+#[derive(Debug, Clone)]
+pub struct UserEntity {
+    pub user_id: i32,
+    pub created_at: String,
+    pub updated_at: String,
+    pub user_uuid: Uuid,
+    pub github_id: i64,
+    pub user_login: String,
+    pub user_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub user_role: Role,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserResponse {
+    user_uuid: Uuid,
+    user_login: String,
+    user_name: Option<String>,
+    avatar_url: Option<String>,
+    user_role: Role,
+}
+
+impl From<UserEntity> for UserResponse {
+    fn from(value: UserEntity) -> Self {
+        Self {
+            user_uuid: value.user_uuid,
+            user_login: value.user_login,
+            user_name: value.user_name,
+            avatar_url: value.avatar_url,
+            user_role: value.user_role,
+        }
+    }
+}
+
+impl UserEntity {
+    pub(crate) fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        let user_uuid = row.get::<_, String>("user_uuid")?;
+        let user_uuid = user_uuid.parse().unwrap();
+        let user_role = row.get::<_, String>("user_role")?;
+        let user_role = user_role.parse().unwrap_or(Role::Reader);
+        Ok(UserEntity {
+            user_id: row.get("user_id")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+            user_uuid,
+            github_id: row.get("github_id")?,
+            user_login: row.get("user_login")?,
+            user_name: row.get("user_name")?,
+            avatar_url: row.get("avatar_url")?,
+            user_role,
+        })
+    }
+
+    /// Create or refresh the user for a GitHub profile.
+    ///
+    /// Logins listed in the configured admins are always admins; when
+    /// no admins are configured the first user becomes an admin.
+    pub async fn upsert_github(
+        pool: &Pool,
+        profile: GithubUser,
+        auth: &Auth,
+    ) -> Result<Self, ServerError> {
+        let select = sql::Select::new()
+            .select("*")
+            .from("users")
+            .where_clause("github_id = ?1");
+
+        let count_users = sql::Select::new().select("COUNT(*)").from("users");
+
+        let insert = sql::Insert::new()
+            .insert_into(
+                "users (created_at, updated_at, user_uuid, github_id, user_login, user_name, avatar_url, user_role)",
+            )
+            .values("(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)");
+
+        let update = sql::Update::new()
+            .update("users")
+            .set("updated_at = ?1, user_login = ?2, user_name = ?3, avatar_url = ?4")
+            .where_clause("github_id = ?5");
+
+        let promote = sql::Update::new()
+            .update("users")
+            .set("user_role = ?1")
+            .where_clause("github_id = ?2");
+
+        let is_admin = auth.admins.contains(&profile.login);
+        let bootstrap_admin = auth.admins.is_empty();
+        let default_role = auth.default_role;
+        let now = UtcDateTime::now().format(&Rfc3339)?;
+        let user = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let exists = tx
+                    .prepare_cached(&select.as_string())?
+                    .exists([profile.id])?;
+                if exists {
+                    tx.execute(
+                        &update.as_string(),
+                        (
+                            now,
+                            profile.login,
+                            profile.name,
+                            profile.avatar_url,
+                            profile.id,
+                        ),
+                    )?;
+                    if is_admin {
+                        tx.execute(&promote.as_string(), (Role::Admin.as_str(), profile.id))?;
+                    }
+                } else {
+                    let first_user = tx
+                        .prepare_cached(&count_users.as_string())?
+                        .query_row([], |row| row.get::<_, i64>(0))?
+                        == 0;
+                    let user_role = if is_admin || (bootstrap_admin && first_user) {
+                        Role::Admin
+                    } else {
+                        default_role
+                    };
+                    tx.execute(
+                        &insert.as_string(),
+                        (
+                            now.clone(),
+                            now,
+                            Uuid::new_v4().to_string(),
+                            profile.id,
+                            profile.login,
+                            profile.name,
+                            profile.avatar_url,
+                            user_role.as_str(),
+                        ),
+                    )?;
+                }
+                let user = tx
+                    .prepare_cached(&select.as_string())?
+                    .query_row([profile.id], UserEntity::from_row)?;
+                tx.commit()?;
+                Ok(user)
+            })
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn find_all(pool: &Pool) -> Result<Vec<Self>, ServerError> {
+        let sql = sql::Select::new()
+            .select("*")
+            .from("users")
+            .order_by("user_login ASC");
+        let users = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&sql.as_string())?;
+                let mut rows = stmt.query([])?;
+                let mut users = Vec::new();
+                while let Some(row) = rows.next()? {
+                    users.push(UserEntity::from_row(row)?);
+                }
+                Ok(users)
+            })
+            .await?;
+        Ok(users)
+    }
+
+    pub async fn find_by_uuid(pool: &Pool, user_uuid: Uuid) -> Result<Self, ServerError> {
+        let query = sql::Select::new()
+            .select("*")
+            .from("users")
+            .where_clause("user_uuid = ?1");
+
+        let content: Result<UserEntity, async_sqlite::Error> = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.query_row([user_uuid.to_string()], UserEntity::from_row)
+            })
+            .await;
+
+        match content {
+            Ok(entity) => Ok(entity),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Grant a role to a user.
+    ///
+    /// The last admin cannot be demoted.
+    pub async fn set_role(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        user_uuid: Uuid,
+        role: Role,
+    ) -> Result<(), ServerError> {
+        Self::change_role(pool, actor.into(), user_uuid, role, AuditAction::RoleGrant).await
+    }
+
+    /// Revoke the role of a user, leaving them a reader.
+    pub async fn revoke_role(
+        pool: &Pool,
+        actor: impl Into<Actor>,
+        user_uuid: Uuid,
+    ) -> Result<(), ServerError> {
+        Self::change_role(
+            pool,
+            actor.into(),
+            user_uuid,
+            Role::Reader,
+            AuditAction::RoleRevoke,
+        )
+        .await
+    }
+
+    async fn change_role(
+        pool: &Pool,
+        actor: Actor,
+        user_uuid: Uuid,
+        role: Role,
+        action: AuditAction,
+    ) -> Result<(), ServerError> {
+        let count_admins = sql::Select::new()
+            .select(
+                "COALESCE(SUM(user_uuid = ?2), 0) AS is_admin, COALESCE(SUM(user_uuid != ?2), 0) AS other_admins",
+            )
+            .from("users")
+            .where_clause("user_role = ?1");
+
+        let update = sql::Update::new()
+            .update("users")
+            .set("user_role = ?1, updated_at = ?2")
+            .where_clause("user_uuid = ?3");
+
+        let select = sql::Select::new()
+            .select("user_login")
+            .from("users")
+            .where_clause("user_uuid = ?1");
+
+        let updated_at = UtcDateTime::now().format(&Rfc3339)?;
+        let affected: Result<Option<()>, async_sqlite::Error> = pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                if role != Role::Admin {
+                    let (is_admin, other_admins) = tx
+                        .prepare_cached(&count_admins.as_string())?
+                        .query_row((Role::Admin.as_str(), user_uuid.to_string()), |row| {
+                            Ok((
+                                row.get::<_, i64>("is_admin")?,
+                                row.get::<_, i64>("other_admins")?,
+                            ))
+                        })?;
+                    if is_admin > 0 && other_admins == 0 {
+                        return Ok(None);
+                    }
+                }
+                tx.execute(
+                    &update.as_string(),
+                    (role.as_str(), updated_at, user_uuid.to_string()),
+                )?;
+                let user_login: String = tx
+                    .prepare_cached(&select.as_string())?
+                    .query_row([user_uuid.to_string()], |row| row.get(0))?;
+                let detail = match action {
+                    AuditAction::RoleRevoke => user_login,
+                    _ => format!("{} {}", user_login, role),
+                };
+                AuditEntity::record(&tx, &actor, action, &user_uuid.to_string(), Some(&detail))?;
+                tx.commit()?;
+                Ok(Some(()))
+            })
+            .await;
+
+        match affected {
+            Ok(Some(())) => Ok(()),
+            Ok(None) => Err(ServerError::Conflict),
+            Err(Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => Err(ServerError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::ServerError;
+use async_sqlite::{Pool, rusqlite};
+use sql_query_builder as sql;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+
+/// Page a user follows changes to, optionally with the pages below it.
+pub struct WatchEntity {
+    pub watch_id: i32,
+    pub created_at: String,
+    pub page_name: String,
+    pub include_subpages: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchResponse {
+    page_name: String,
+    include_subpages: bool,
+    created_at: String,
+}
+
+impl From<WatchEntity> for WatchResponse {
+    fn from(value: WatchEntity) -> Self {
+        Self {
+            page_name: value.page_name,
+            include_subpages: value.include_subpages,
+            created_at: value.created_at,
+        }
+    }
+}
+
+const COLUMNS: &str = "watch_id, created_at, page_name, include_subpages";
+
+impl WatchEntity {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            watch_id: row.get("watch_id")?,
+            created_at: row.get("created_at")?,
+            page_name: row.get("page_name")?,
+            include_subpages: row.get("include_subpages")?,
+        })
+    }
+
+    /// Watch a page by name, watching it again only changes whether
+    /// the pages below it are included.
+    pub async fn watch(
+        pool: &Pool,
+        user_id: i32,
+        page_name: String,
+        include_subpages: bool,
+    ) -> Result<Self, ServerError> {
+        let insert = sql::Insert::new()
+            .insert_into("watches (created_at, user_id, page_name, include_subpages)")
+            .values(
+                "(?1, ?2, ?3, ?4) ON CONFLICT (user_id, page_name) DO UPDATE SET include_subpages = excluded.include_subpages",
+            );
+        let select = sql::Select::new()
+            .select(COLUMNS)
+            .from("watches")
+            .where_clause("user_id = ?1")
+            .where_clause("page_name = ?2");
+
+        let created_at = UtcDateTime::now().format(&Rfc3339)?;
+        let watch = pool
+            .conn(move |conn| {
+                conn.prepare_cached(&insert.as_string())?.execute((
+                    &created_at,
+                    user_id,
+                    &page_name,
+                    include_subpages,
+                ))?;
+                conn.prepare_cached(&select.as_string())?
+                    .query_row((user_id, &page_name), WatchEntity::from_row)
+            })
+            .await?;
+        Ok(watch)
+    }
+
+    pub async fn find_all_by_user_id(pool: &Pool, user_id: i32) -> Result<Vec<Self>, ServerError> {
+        let query = sql::Select::new()
+            .select(COLUMNS)
+            .from("watches")
+            .where_clause("user_id = ?1")
+            .order_by("page_name ASC");
+        let watches = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([user_id])?;
+                let mut watches = Vec::new();
+                while let Some(row) = rows.next()? {
+                    watches.push(WatchEntity::from_row(row)?);
+                }
+                Ok(watches)
+            })
+            .await?;
+        Ok(watches)
+    }
+
+    pub async fn delete(pool: &Pool, user_id: i32, page_name: String) -> Result<(), ServerError> {
+        let query = sql::Delete::new()
+            .delete_from("watches")
+            .where_clause("user_id = ?1")
+            .where_clause("page_name = ?2");
+
+        let affected = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.execute((user_id, page_name))
+            })
+            .await?;
+
+        if affected == 0 {
+            Err(ServerError::NotFound)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::error::ServerError;
+use async_sqlite::{Pool, rusqlite};
+use sql_query_builder as sql;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+/// Payload sent to a webhook and the outcome of the latest attempt.
+pub struct WebhookDeliveryEntity {
+    pub delivery_id: i64,
+    pub created_at: String,
+    pub delivery_uuid: Uuid,
+    pub webhook_url: String,
+    pub event_name: String,
+    pub payload: String,
+    pub attempt_count: i64,
+    pub attempted_at: Option<String>,
+    pub status_code: Option<i64>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryResponse {
+    delivery_uuid: Uuid,
+    created_at: String,
+    webhook_url: String,
+    event_name: String,
+    payload: String,
+    attempt_count: i64,
+    attempted_at: Option<String>,
+    status_code: Option<i64>,
+    last_error: Option<String>,
+    delivered_at: Option<String>,
+}
+
+impl From<WebhookDeliveryEntity> for WebhookDeliveryResponse {
+    fn from(value: WebhookDeliveryEntity) -> Self {
+        Self {
+            delivery_uuid: value.delivery_uuid,
+            created_at: value.created_at,
+            webhook_url: value.webhook_url,
+            event_name: value.event_name,
+            payload: value.payload,
+            attempt_count: value.attempt_count,
+            attempted_at: value.attempted_at,
+            status_code: value.status_code,
+            last_error: value.last_error,
+            delivered_at: value.delivered_at,
+        }
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct WebhookDeliveryQuery {
+    pub event: Option<String>,
+    /// Only deliveries that have not succeeded yet.
+    #[serde(default)]
+    pub failed: bool,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+const COLUMNS: &str = "delivery_id, created_at, delivery_uuid, webhook_url, event_name, payload, \
+    attempt_count, attempted_at, status_code, last_error, delivered_at";
+
+impl WebhookDeliveryEntity {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let delivery_uuid = row.get::<_, String>("delivery_uuid")?;
+        Ok(Self {
+            delivery_id: row.get("delivery_id")?,
+            created_at: row.get("created_at")?,
+            delivery_uuid: delivery_uuid.parse().unwrap(),
+            webhook_url: row.get("webhook_url")?,
+            event_name: row.get("event_name")?,
+            payload: row.get("payload")?,
+            attempt_count: row.get("attempt_count")?,
+            attempted_at: row.get("attempted_at")?,
+            status_code: row.get("status_code")?,
+            last_error: row.get("last_error")?,
+            delivered_at: row.get("delivered_at")?,
+        })
+    }
+
+    /// Log a payload before its first attempt and return its id.
+    pub async fn create(
+        pool: &Pool,
+        delivery_uuid: Uuid,
+        webhook_url: String,
+        event_name: String,
+        payload: String,
+    ) -> Result<i64, ServerError> {
+        let query = sql::Insert::new()
+            .insert_into(
+                "webhook_deliveries (created_at, delivery_uuid, webhook_url, event_name, payload)",
+            )
+            .values("(?1, ?2, ?3, ?4, ?5)");
+
+        let created_at = UtcDateTime::now().format(&Rfc3339)?;
+        let delivery_id = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                stmt.execute((
+                    created_at,
+                    delivery_uuid.to_string(),
+                    webhook_url,
+                    event_name,
+                    payload,
+                ))?;
+                Ok(conn.last_insert_rowid())
+            })
+            .await?;
+        Ok(delivery_id)
+    }
+
+    /// Record the response status or the error of an attempt.
+    pub async fn record_attempt(
+        pool: &Pool,
+        delivery_id: i64,
+        status_code: Option<u16>,
+        error: Option<String>,
+        delivered: bool,
+    ) -> Result<(), ServerError> {
+        let query = sql::Update::new()
+            .update("webhook_deliveries")
+            .set(
+                "attempt_count = attempt_count + 1, attempted_at = ?1, status_code = ?2, last_error = ?3, \
+                delivered_at = CASE WHEN ?4 THEN ?1 ELSE NULL END",
+            )
+            .where_clause("delivery_id = ?5");
+
+        let attempted_at = UtcDateTime::now().format(&Rfc3339)?;
+        pool.conn(move |conn| {
+            let mut stmt = conn.prepare_cached(&query.as_string())?;
+            stmt.execute((attempted_at, status_code, error, delivered, delivery_id))
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Deliveries that have not succeeded and may still be retried,
+    /// oldest first.
+    pub async fn find_pending(pool: &Pool, max_attempts: u32) -> Result<Vec<Self>, ServerError> {
+        let query = sql::Select::new()
+            .select(COLUMNS)
+            .from("webhook_deliveries")
+            .where_clause("delivered_at IS NULL")
+            .where_clause("attempt_count < ?1")
+            // other client errors are not retried
+            .where_clause(
+                "(status_code IS NULL OR status_code >= 500 OR status_code IN (408, 429))",
+            )
+            .order_by("delivery_id");
+
+        let deliveries = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&query.as_string())?;
+                let mut rows = stmt.query([max_attempts])?;
+                let mut deliveries = Vec::new();
+                while let Some(row) = rows.next()? {
+                    deliveries.push(Self::from_row(row)?);
+                }
+                Ok(deliveries)
+            })
+            .await?;
+        Ok(deliveries)
+    }
+
+    /// Latest deliveries first.
+    pub async fn find_all(
+        pool: &Pool,
+        query: WebhookDeliveryQuery,
+    ) -> Result<Vec<Self>, ServerError> {
+        let mut sql = sql::Select::new()
+            .select(COLUMNS)
+            .from("webhook_deliveries");
+        let mut params = Vec::new();
+        if let Some(event) = query.event {
+            params.push(event);
+            sql = sql.where_clause(&format!("event_name = ?{}", params.len()));
+        }
+        if query.failed {
+            sql = sql.where_clause("delivered_at IS NULL");
+        }
+
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let offset = query.offset.unwrap_or_default();
+        let sql = sql.order_by(&format!(
+            "delivery_id DESC LIMIT {} OFFSET {}",
+            limit, offset
+        ));
+
+        let deliveries = pool
+            .conn(move |conn| {
+                let mut stmt = conn.prepare_cached(&sql.as_string())?;
+                let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+                let mut deliveries = Vec::new();
+                while let Some(row) = rows.next()? {
+                    deliveries.push(Self::from_row(row)?);
+                }
+                Ok(deliveries)
+            })
+            .await?;
+        Ok(deliveries)
+    }
+}
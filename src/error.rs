@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use oauth_axum::error::OauthError;
@@ -16,10 +16,20 @@ pub enum ServerError {
     NoGeneratedOauthUrl,
     #[error("failed to generate oauth url")]
     GenerateOauthUrl,
+    #[error("invalid or expired oauth state")]
+    InvalidOauthState,
+    #[error("bad request")]
+    BadRequest,
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("forbidden")]
+    Forbidden,
     #[error("not found")]
     NotFound,
     #[error("conflict")]
     Conflict,
+    #[error("too many requests, retry after {0} seconds")]
+    TooManyRequests(u64),
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
@@ -32,6 +42,8 @@ pub enum ServerError {
     Sqlite(#[from] async_sqlite::Error),
     #[error(transparent)]
     Http(#[from] axum::http::Error),
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
 }
 
 // Implement `IntoResponse` for the error
@@ -39,8 +51,20 @@ impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
         tracing::error!(error = ?self);
         match self {
+            Self::BadRequest => (StatusCode::BAD_REQUEST, "Bad Request").into_response(),
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+            Self::Forbidden => (StatusCode::FORBIDDEN, "Forbidden").into_response(),
             Self::NotFound => (StatusCode::NOT_FOUND, "Not Found").into_response(),
             Self::Conflict => (StatusCode::CONFLICT, "Conflict").into_response(),
+            Self::TooManyRequests(retry_after) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "Too Many Requests",
+            )
+                .into_response(),
+            Self::InvalidOauthState => {
+                (StatusCode::BAD_REQUEST, "Invalid or expired OAuth state").into_response()
+            }
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response(),
         }
     }
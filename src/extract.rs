@@ -0,0 +1,198 @@
+//! Extract searchable text from attachments.
+use crate::helpers::{html_to_text, sanitize_html};
+use axum::body::Bytes;
+use kuchiki::{parse_html, traits::*};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    time::Duration,
+};
+
+/// Maximum length of the text indexed for an attachment.
+const MAX_TEXT_LENGTH: usize = 1024 * 1024;
+
+/// Larger PDFs are stored without extracting their text.
+const MAX_PDF_SIZE: usize = 16 * 1024 * 1024;
+
+/// Time allowed for extracting the text of a PDF.
+const PDF_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Extract the text of an attachment, `None` when the content is not
+/// text-like or contains no text.
+///
+/// The content type sent by browsers is often generic, so the file
+/// name is used to guess the type of `application/octet-stream` uploads.
+pub async fn extract_text(file_name: &str, content_type: &str, content: &Bytes) -> Option<String> {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let essence = if essence.is_empty() || essence == "application/octet-stream" {
+        mime_guess::from_path(file_name)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_owned()
+    } else {
+        essence
+    };
+
+    let text = match essence.as_str() {
+        "text/html" | "application/xhtml+xml" => {
+            let html = sanitize_html(&String::from_utf8_lossy(content));
+            html_to_text(&parse_html().one(html))
+        }
+        "application/pdf" => extract_pdf(file_name, content.clone()).await?,
+        "application/json" | "application/xml" => String::from_utf8_lossy(content).into_owned(),
+        essence if essence.starts_with("text/") => String::from_utf8_lossy(content).into_owned(),
+        _ => return None,
+    };
+
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let mut end = text.len().min(MAX_TEXT_LENGTH);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(text[..end].to_owned())
+}
+
+/// Extract the text layer of a PDF on a blocking thread.
+///
+/// The parser may panic or spin on malformed files, those are stored
+/// without text. A timed out extraction keeps its thread until it ends.
+async fn extract_pdf(file_name: &str, content: Bytes) -> Option<String> {
+    if content.len() > MAX_PDF_SIZE {
+        tracing::debug!(file_name = %file_name, size = %content.len(), "extract::pdf");
+        return None;
+    }
+    let task = tokio::task::spawn_blocking(move || {
+        catch_unwind(AssertUnwindSafe(|| {
+            pdf_extract::extract_text_from_mem(&content)
+        }))
+    });
+    let error = match tokio::time::timeout(PDF_TIMEOUT, task).await {
+        Ok(Ok(Ok(Ok(text)))) => return Some(text),
+        Ok(Ok(Ok(Err(e)))) => e.to_string(),
+        Ok(Ok(Err(_))) => "panicked".to_owned(),
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "timed out".to_owned(),
+    };
+    tracing::warn!(error = %error, file_name = %file_name, "extract::pdf");
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_PDF_SIZE, MAX_TEXT_LENGTH, extract_text};
+    use axum::body::Bytes;
+
+    #[tokio::test]
+    async fn extracts_text_like_content() {
+        let text = |file_name, content_type, content: &'static [u8]| async move {
+            extract_text(file_name, content_type, &Bytes::from_static(content)).await
+        };
+        assert_eq!(
+            text("notes.txt", "text/plain; charset=utf-8", b" pager duty \n").await,
+            Some("pager duty".to_owned())
+        );
+        // generic uploads are typed by their file name
+        assert_eq!(
+            text("data.csv", "application/octet-stream", b"host,port").await,
+            Some("host,port".to_owned())
+        );
+        assert_eq!(
+            text(
+                "page.html",
+                "text/html",
+                b"<p>pager <script>x()</script>duty</p>"
+            )
+            .await,
+            Some("pager duty".to_owned())
+        );
+        assert_eq!(text("logo.png", "image/png", b"\x89PNG").await, None);
+        assert_eq!(text("empty.txt", "text/plain", b"  \n").await, None);
+    }
+
+    #[tokio::test]
+    async fn truncates_long_text_on_a_character_boundary() {
+        // the limit falls in the middle of a two byte character
+        let content = Bytes::from(format!("a{}", "é".repeat(MAX_TEXT_LENGTH)));
+        let text = extract_text("long.txt", "text/plain", &content)
+            .await
+            .unwrap();
+        assert_eq!(text.len(), MAX_TEXT_LENGTH - 1);
+        assert!(text.ends_with('é'));
+    }
+
+    /// Single page PDF showing `text`, with `padding` bytes of
+    /// comment before its objects.
+    fn pdf(text: &str, padding: usize) -> Bytes {
+        let stream = format!("BT /F1 12 Tf 72 712 Td ({}) Tj ET", text);
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_owned(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] \
+             /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>"
+                .to_owned(),
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                stream.len(),
+                stream
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_owned(),
+        ];
+        let mut pdf = format!("%PDF-1.4\n%{}\n", " ".repeat(padding));
+        let mut offsets = Vec::new();
+        for (n, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", n + 1, object));
+        }
+        let xref = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            pdf.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        pdf.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        ));
+        Bytes::from(pdf)
+    }
+
+    #[tokio::test]
+    async fn extracts_pdf_text_up_to_the_size_limit() {
+        let content = pdf("pager duty", 0);
+        assert_eq!(
+            extract_text("rotation.pdf", "application/pdf", &content).await,
+            Some("pager duty".to_owned())
+        );
+
+        let content = pdf("pager duty", MAX_PDF_SIZE);
+        assert!(content.len() > MAX_PDF_SIZE);
+        assert_eq!(
+            extract_text("rotation.pdf", "application/pdf", &content).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_malformed_pdfs() {
+        let content = Bytes::from_static(b"%PDF-1.7\nnot a pdf after all");
+        assert_eq!(
+            extract_text("broken.pdf", "application/pdf", &content).await,
+            None
+        );
+        let content = Bytes::from_static(b"\x00\x01\x02");
+        assert_eq!(
+            extract_text("broken.pdf", "application/octet-stream", &content).await,
+            None
+        );
+    }
+}
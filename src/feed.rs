@@ -0,0 +1,205 @@
+//! Atom and RSS feeds.
+use crate::helpers::escape_html;
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use time::{
+    OffsetDateTime,
+    format_description::well_known::{Rfc2822, Rfc3339},
+};
+
+pub struct FeedEntry {
+    /// Permanent identifier, such as `urn:uuid:...`.
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub updated: String,
+    pub author: Option<String>,
+    pub summary: Option<String>,
+}
+
+pub struct Feed {
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    /// Time of the latest change, also used when there are no entries.
+    pub updated: String,
+    pub entries: Vec<FeedEntry>,
+}
+
+impl Feed {
+    pub fn to_atom(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("  <id>{}</id>\n", escape_html(&self.id)));
+        xml.push_str(&format!("  <title>{}</title>\n", escape_html(&self.title)));
+        xml.push_str(&format!("  <link href=\"{}\"/>\n", escape_html(&self.link)));
+        xml.push_str(&format!(
+            "  <updated>{}</updated>\n",
+            escape_html(&self.updated)
+        ));
+        for entry in &self.entries {
+            xml.push_str("  <entry>\n");
+            xml.push_str(&format!("    <id>{}</id>\n", escape_html(&entry.id)));
+            xml.push_str(&format!(
+                "    <title>{}</title>\n",
+                escape_html(&entry.title)
+            ));
+            xml.push_str(&format!(
+                "    <link href=\"{}\"/>\n",
+                escape_html(&entry.link)
+            ));
+            xml.push_str(&format!(
+                "    <updated>{}</updated>\n",
+                escape_html(&entry.updated)
+            ));
+            // atom requires an author on the feed or on every entry
+            let author = entry.author.as_deref().unwrap_or("anonymous");
+            xml.push_str(&format!(
+                "    <author><name>{}</name></author>\n",
+                escape_html(author)
+            ));
+            if let Some(summary) = &entry.summary {
+                xml.push_str(&format!(
+                    "    <summary>{}</summary>\n",
+                    escape_html(summary)
+                ));
+            }
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    /// RSS 2.0 rendering for readers and integrations without Atom
+    /// support, links should be absolute.
+    pub fn to_rss(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+        xml.push_str("  <channel>\n");
+        xml.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_html(&self.title)
+        ));
+        xml.push_str(&format!("    <link>{}</link>\n", escape_html(&self.link)));
+        xml.push_str(&format!(
+            "    <description>{}</description>\n",
+            escape_html(&self.title)
+        ));
+        xml.push_str(&format!(
+            "    <lastBuildDate>{}</lastBuildDate>\n",
+            rfc2822(&self.updated)
+        ));
+        for entry in &self.entries {
+            xml.push_str("    <item>\n");
+            xml.push_str(&format!(
+                "      <guid isPermaLink=\"false\">{}</guid>\n",
+                escape_html(&entry.id)
+            ));
+            xml.push_str(&format!(
+                "      <title>{}</title>\n",
+                escape_html(&entry.title)
+            ));
+            xml.push_str(&format!(
+                "      <link>{}</link>\n",
+                escape_html(&entry.link)
+            ));
+            xml.push_str(&format!(
+                "      <pubDate>{}</pubDate>\n",
+                rfc2822(&entry.updated)
+            ));
+            // the RSS author element must be an email address
+            if let Some(author) = &entry.author {
+                xml.push_str(&format!(
+                    "      <dc:creator>{}</dc:creator>\n",
+                    escape_html(author)
+                ));
+            }
+            if let Some(summary) = &entry.summary {
+                xml.push_str(&format!(
+                    "      <description>{}</description>\n",
+                    escape_html(summary)
+                ));
+            }
+            xml.push_str("    </item>\n");
+        }
+        xml.push_str("  </channel>\n");
+        xml.push_str("</rss>\n");
+        xml
+    }
+}
+
+/// RSS dates use RFC 2822, timestamps that don't parse are kept.
+fn rfc2822(timestamp: &str) -> String {
+    OffsetDateTime::parse(timestamp, &Rfc3339)
+        .ok()
+        .and_then(|date| date.format(&Rfc2822).ok())
+        .unwrap_or_else(|| timestamp.to_owned())
+}
+
+impl IntoResponse for Feed {
+    fn into_response(self) -> Response {
+        (
+            [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+            self.to_atom(),
+        )
+            .into_response()
+    }
+}
+
+/// Feed rendered as RSS rather than Atom.
+pub struct RssFeed(pub Feed);
+
+impl IntoResponse for RssFeed {
+    fn into_response(self) -> Response {
+        (
+            [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+            self.0.to_rss(),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Feed, FeedEntry};
+
+    #[test]
+    fn escapes_atom() {
+        let feed = Feed {
+            id: "urn:twilite:test".to_owned(),
+            title: "Q&A".to_owned(),
+            link: "/".to_owned(),
+            updated: "2025-01-01T00:00:00Z".to_owned(),
+            entries: vec![FeedEntry {
+                id: "urn:uuid:1".to_owned(),
+                title: "<Runbook>".to_owned(),
+                link: "/wiki/a?b=1&c=2".to_owned(),
+                updated: "2025-01-01T00:00:00Z".to_owned(),
+                author: None,
+                summary: Some("a \"quoted\" text".to_owned()),
+            }],
+        };
+        let xml = feed.to_atom();
+        assert!(xml.contains("<title>Q&amp;A</title>"));
+        assert!(xml.contains("<title>&lt;Runbook&gt;</title>"));
+        assert!(xml.contains("<link href=\"/wiki/a?b=1&amp;c=2\"/>"));
+        assert!(xml.contains("<author><name>anonymous</name></author>"));
+        assert!(xml.contains("<summary>a &quot;quoted&quot; text</summary>"));
+    }
+
+    #[test]
+    fn formats_rss_dates() {
+        let feed = Feed {
+            id: "urn:twilite:test".to_owned(),
+            title: "Recent changes".to_owned(),
+            link: "https://wiki.example.com/".to_owned(),
+            updated: "2025-03-04T05:06:07Z".to_owned(),
+            entries: vec![],
+        };
+        let xml = feed.to_rss();
+        assert!(xml.contains("<lastBuildDate>Tue, 04 Mar 2025 05:06:07 +0000</lastBuildDate>"));
+        assert!(xml.contains("<link>https://wiki.example.com/</link>"));
+    }
+}
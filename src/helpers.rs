@@ -2,15 +2,49 @@ use crate::error::ServerError;
 use kuchiki::NodeRef;
 use kuchiki::parse_html;
 use kuchiki::traits::*;
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
 use regex::Regex;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::sync::LazyLock;
+use time::{
+    Date, OffsetDateTime,
+    format_description::{self, well_known::Rfc3339},
+};
 
 static WIKI_WORD_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"([A-Z][a-z0-9]+(?:[A-Z][a-z0-9]*)+)").unwrap());
 
+/// Page names in text: a CamelCase word, a path of capitalized words
+/// such as `Team/Oncall/Runbook`, or a name relative to the page
+/// starting with `./` or `../`.
+static WIKI_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"((?:\.\.?/)*)([A-Z][a-z0-9]+(?:[A-Z][a-z0-9]*)*(?:/[A-Z][a-z0-9]+(?:[A-Z][a-z0-9]*)*)*)",
+    )
+    .unwrap()
+});
+
 const PREVIEW_LENGTH: usize = 256;
 
+/// Characters escaped in a page name used as a path.
+const PATH_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Markers placed around matched terms by `highlight()` and `snippet()`,
+/// control characters never appear in page text.
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
 pub fn sanitize_html(dirty_html: &str) -> String {
     use ammonia::Builder;
     let mut builder = Builder::default();
@@ -87,20 +121,228 @@ pub fn trim_preview_text(input: &str) -> &str {
     }
 }
 
+/// Opaque cursor for the position after a row in a listing, holding
+/// the value the listing is sorted by and the row id breaking ties.
+pub fn encode_cursor(value: &str, id: i32) -> String {
+    format!("{}:{}", id, value)
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Value and row id of a cursor from [`encode_cursor`].
+pub fn decode_cursor(cursor: &str) -> Option<(String, i32)> {
+    if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+        return None;
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let decoded = String::from_utf8(bytes).ok()?;
+    let (id, value) = decoded.split_once(':')?;
+    Some((value.to_owned(), id.parse().ok()?))
+}
+
+/// Maximum length of a tag in characters.
+const MAX_TAG_LENGTH: usize = 64;
+
+/// Normalize a tag to lowercase with runs of whitespace replaced by
+/// `-`, `None` when it is empty, too long or contains a `/` or `,`.
+pub fn normalize_tag(input: &str) -> Option<String> {
+    let tag = input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH || tag.contains(['/', ',']) {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+/// Escape text for use in HTML content and attribute values.
+pub fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape text containing match markers and wrap the matches in `<mark>`.
+pub fn mark_matches(input: &str) -> String {
+    escape_html(input)
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+/// Generate a random secret token.
+pub fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Hash a secret token for storage.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
 pub fn transform_page(input: &str) -> Result<(NodeRef, Option<String>), ServerError> {
+    transform_page_in(input, None)
+}
+
+/// Transform the content of the page `base`, relative links are
+/// resolved against its name so they point to the same pages after
+/// the content is moved or copied.
+pub fn transform_page_in(
+    input: &str,
+    base: Option<&str>,
+) -> Result<(NodeRef, Option<String>), ServerError> {
     let mut document = parse_html().from_utf8().read_from(&mut input.as_bytes())?;
-    rewrite_wiki_links(&mut document)?;
+    if let Some(base) = base {
+        resolve_relative_links(&document, base);
+    }
+    rewrite_wiki_links(&mut document, base.unwrap_or_default())?;
     let toc = assign_ids_and_generate_toc(&document);
     Ok((document, toc))
 }
 
+/// Whether a timestamp is in RFC3339 format.
+pub fn is_valid_timestamp(timestamp: &str) -> bool {
+    OffsetDateTime::parse(timestamp, &Rfc3339).is_ok()
+}
+
+/// Whether a value is a `YYYY-MM-DD` date or an RFC3339 timestamp.
+pub fn is_valid_date(value: &str) -> bool {
+    let date = format_description::parse("[year]-[month]-[day]").expect("valid date format");
+    Date::parse(value, &date).is_ok() || is_valid_timestamp(value)
+}
+
+/// Whether a page name is usable as a path: not empty, without
+/// leading, trailing or repeated slashes and without `.` or `..`
+/// segments.
+pub fn is_valid_page_name(page_name: &str) -> bool {
+    !page_name.is_empty()
+        && page_name
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+}
+
+/// Resolve a page name relative to the page `base` as a browser
+/// resolves a relative URL, so `Sibling` and `./Sibling` share the
+/// parent of `base` and each `../` goes up a level.
+pub fn resolve_page_name(base: &str, link: &str) -> Option<String> {
+    let mut segments: Vec<&str> = base.split('/').filter(|s| !s.is_empty()).collect();
+    segments.pop();
+    for segment in link.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+/// Ancestors of a page from the top, `Team/Oncall/Runbook` has
+/// `Team` and `Team/Oncall`.
+pub fn page_ancestors(page_name: &str) -> Vec<String> {
+    page_name
+        .match_indices('/')
+        .map(|(index, _)| page_name[..index].to_owned())
+        .collect()
+}
+
+/// Point relative anchors at the absolute path of the page they
+/// resolve to.
+fn resolve_relative_links(document: &NodeRef, base: &str) {
+    let Ok(anchors) = document.select("a[href]") else {
+        return;
+    };
+    for anchor in anchors {
+        let mut attributes = anchor.attributes.borrow_mut();
+        let Some(href) = attributes.get("href") else {
+            continue;
+        };
+        let path_end = href.find(['#', '?']).unwrap_or(href.len());
+        let (path, suffix) = href.split_at(path_end);
+        // absolute paths, fragments and URLs with a scheme are kept
+        if path.is_empty() || path.starts_with('/') || path.split('/').next().unwrap().contains(':')
+        {
+            continue;
+        }
+        let link = percent_decode_str(path).decode_utf8_lossy();
+        if let Some(page_name) = resolve_page_name(base, &link) {
+            let href = format!("{}{}", wiki_path(&page_name), suffix);
+            attributes.insert("href", href);
+        }
+    }
+}
+
+/// Names of the wiki pages linked from a document.
+pub fn wiki_links(document: &NodeRef) -> Vec<String> {
+    let mut links = Vec::new();
+    let Ok(anchors) = document.select("a[href]") else {
+        return links;
+    };
+    for anchor in anchors {
+        let attributes = anchor.attributes.borrow();
+        let Some(path) = attributes
+            .get("href")
+            .and_then(|href| href.strip_prefix("/wiki/"))
+        else {
+            continue;
+        };
+        let path = path.split(['#', '?']).next().unwrap_or_default();
+        let name = percent_decode_str(path).decode_utf8_lossy().into_owned();
+        if !name.is_empty() && !links.contains(&name) {
+            links.push(name);
+        }
+    }
+    links
+}
+
+/// Path of the wiki page with a name.
+pub fn wiki_path(page_name: &str) -> String {
+    format!("/wiki/{}", utf8_percent_encode(page_name, PATH_SET))
+}
+
+/// Similarity of two strings by the trigrams they share, from 0 to 1.
+pub fn trigram_similarity(a: &str, b: &str) -> f64 {
+    fn trigrams(s: &str) -> HashSet<[char; 3]> {
+        let padded: Vec<char> = format!("  {} ", s.to_lowercase()).chars().collect();
+        padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+    }
+    let (a, b) = (trigrams(a), trigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        0.0
+    } else {
+        a.intersection(&b).count() as f64 / union as f64
+    }
+}
+
 pub fn stringify_doc(document: &NodeRef) -> Result<String, ServerError> {
     let mut output = Vec::new();
     document.serialize(&mut output)?;
     Ok(String::from_utf8(output)?)
 }
 
-fn rewrite_wiki_links(document: &mut NodeRef) -> Result<(), ServerError> {
+fn rewrite_wiki_links(document: &mut NodeRef, base: &str) -> Result<(), ServerError> {
     for css_match in document.descendants().text_nodes() {
         let parent_is_anchor = css_match.as_node().ancestors().any(|a| {
             a.as_element()
@@ -109,14 +351,26 @@ fn rewrite_wiki_links(document: &mut NodeRef) -> Result<(), ServerError> {
         });
         if !parent_is_anchor {
             let text = css_match.borrow().clone();
-            let replaced = WIKI_WORD_REGEX.replace_all(text.as_ref(), |caps: &regex::Captures| {
-                let wiki_word = &caps[1];
+            let replaced = WIKI_NAME_REGEX.replace_all(text.as_ref(), |caps: &regex::Captures| {
+                let (relative, wiki_word) = (&caps[1], &caps[2]);
                 let full_match = caps.get(0).unwrap();
                 let start = full_match.start();
-                if start > 0 && text.as_bytes()[start - 1] == b'!' {
-                    wiki_word.to_string()
+                // a single word must be CamelCase, `!` escapes a name and
+                // a name after `/` is part of a longer path such as a URL
+                let is_name = !relative.is_empty()
+                    || wiki_word.contains('/')
+                    || WIKI_WORD_REGEX.find(wiki_word).map(|m| m.len()) == Some(wiki_word.len());
+                let escaped = start > 0 && matches!(text.as_bytes()[start - 1], b'!' | b'/');
+                let page_name = if relative.is_empty() {
+                    Some(wiki_word.to_owned())
                 } else {
-                    format!("<a href=\"/wiki/{}\">{}</a>", wiki_word, wiki_word)
+                    resolve_page_name(base, full_match.as_str())
+                };
+                match page_name {
+                    Some(page_name) if is_name && !escaped => {
+                        format!("<a href=\"{}\">{}</a>", wiki_path(&page_name), wiki_word)
+                    }
+                    _ => full_match.as_str().to_owned(),
                 }
             });
 
@@ -293,9 +547,72 @@ fn assign_ids_and_generate_toc(document: &NodeRef) -> Option<String> {
 
 #[cfg(test)]
 mod test {
-    use crate::helpers::{stringify_doc, transform_page};
+    use crate::helpers::{
+        decode_cursor, encode_cursor, is_valid_date, is_valid_page_name, mark_matches,
+        normalize_tag, page_ancestors, resolve_page_name, stringify_doc, transform_page,
+        transform_page_in, trigram_similarity, wiki_links,
+    };
     use anyhow::Result;
 
+    #[test]
+    fn collects_wiki_links() -> Result<()> {
+        let html = r#"<p>See WikiPage, <a href="/wiki/Team%2FOncall#pager">oncall</a> and <a href="/wiki/WikiPage">again</a>.</p>"#;
+        let document = transform_page(html)?.0;
+        assert_eq!(wiki_links(&document), vec!["WikiPage", "Team/Oncall"]);
+        Ok(())
+    }
+
+    #[test]
+    fn hierarchical_names() -> Result<()> {
+        assert_eq!(
+            resolve_page_name("Team/Oncall/Runbook", "./Escalation"),
+            Some("Team/Oncall/Escalation".to_owned())
+        );
+        assert_eq!(
+            resolve_page_name("Team/Oncall/Runbook", "../Holidays"),
+            Some("Team/Holidays".to_owned())
+        );
+        assert_eq!(resolve_page_name("Runbook", "../.."), None);
+        assert_eq!(
+            page_ancestors("Team/Oncall/Runbook"),
+            vec!["Team", "Team/Oncall"]
+        );
+        assert!(is_valid_page_name("Team/Oncall"));
+        assert!(!is_valid_page_name("Team//Oncall"));
+        assert!(!is_valid_page_name("/Team"));
+
+        let html = r#"<p>See Team/Oncall/Runbook, ./Escalation, <a href="Pager#setup">pager</a> and https://example.com/Team/Page.</p>"#;
+        let html = stringify_doc(&transform_page_in(html, Some("Team/Oncall/Runbook"))?.0)?;
+        assert!(html.contains(r#"<a href="/wiki/Team/Oncall/Runbook">Team/Oncall/Runbook</a>"#));
+        assert!(html.contains(r#"<a href="/wiki/Team/Oncall/Escalation">Escalation</a>"#));
+        assert!(html.contains(r#"<a href="/wiki/Team/Oncall/Pager#setup">pager</a>"#));
+        assert!(html.contains("https://example.com/Team/Page."));
+        Ok(())
+    }
+
+    #[test]
+    fn similar_names() {
+        assert_eq!(trigram_similarity("Runbook", "runbook"), 1.0);
+        assert!(trigram_similarity("Runbok", "Runbook") > 0.4);
+        assert!(trigram_similarity("Runbook", "Holiday") < 0.1);
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize_tag("  On  Call "), Some("on-call".to_owned()));
+        assert_eq!(normalize_tag("Ops"), Some("ops".to_owned()));
+        assert_eq!(normalize_tag("   "), None);
+        assert_eq!(normalize_tag("a/b"), None);
+    }
+
+    #[test]
+    fn marks_matches() {
+        assert_eq!(
+            mark_matches("a <b> \u{2}match\u{3} & more"),
+            "a &lt;b&gt; <mark>match</mark> &amp; more"
+        );
+    }
+
     #[test]
     fn html_wiki_links() -> Result<()> {
         let html = r#"<p>This is a WikiPage with SomeOtherPage inside a paragraph.</p>"#;
@@ -406,4 +723,26 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn round_trips_cursors() {
+        let cursor = encode_cursor("Team/Oncall: Runbook", 42);
+        assert!(cursor.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(
+            decode_cursor(&cursor),
+            Some(("Team/Oncall: Runbook".to_owned(), 42))
+        );
+        assert_eq!(decode_cursor("zz"), None);
+        assert_eq!(decode_cursor("abc"), None);
+        assert_eq!(decode_cursor(""), None);
+    }
+
+    #[test]
+    fn validates_dates() {
+        assert!(is_valid_date("2025-10-01"));
+        assert!(is_valid_date("2025-10-01T08:30:00Z"));
+        assert!(!is_valid_date("2025-13-01"));
+        assert!(!is_valid_date("yesterday"));
+        assert!(!is_valid_date(""));
+    }
 }
@@ -1,7 +1,14 @@
+pub mod auth;
 pub mod config;
+pub mod csrf;
 pub mod entity;
 pub mod error;
+pub mod extract;
+pub mod feed;
 pub mod helpers;
 pub mod migrations;
+pub mod query;
+pub mod rate_limit;
 pub mod routes;
 pub mod server;
+pub mod webhook;
@@ -1,10 +1,15 @@
 use anyhow::{Context, Result};
-use async_sqlite::{ClientBuilder, JournalMode};
+use async_sqlite::{JournalMode, PoolBuilder};
 use clap::Parser;
 use std::path::{Path, PathBuf};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use twilite::{config::*, migrations, server::Server};
+use twilite::{
+    config::*,
+    entity::{audit::Actor, search::SearchEntity},
+    migrations,
+    server::Server,
+};
 
 const DEFAULT_LOG_LEVEL: &str = "twilite=info";
 
@@ -58,15 +63,45 @@ async fn run() -> Result<()> {
 
     tracing::info!(database = %config.database.path);
 
-    let mut db_client = ClientBuilder::new()
+    let writer = PoolBuilder::new()
         .path(&config.database.path)
         .journal_mode(JournalMode::Wal)
+        .num_conns(1)
         .open()
         .await
         .with_context(|| format!("unable to initialize database: {}", &config.database.path))?;
 
-    migrations::migrate_client(&mut db_client).await?;
-    Server::start(config, db_client, args.open).await
+    migrations::migrate_pool(&writer).await?;
+
+    let tokenizer = config.search.tokenizer;
+    if let Some(Command::Reindex) = args.command {
+        let report = SearchEntity::reindex(&writer, Actor::default(), tokenizer).await?;
+        tracing::info!(
+            tokenizer = %report.tokenizer,
+            pages = %report.pages,
+            files = %report.files,
+            "search::reindexed",
+        );
+        return Ok(());
+    }
+    let current = SearchEntity::current_tokenizer(&writer).await?;
+    if current.as_deref() != Some(tokenizer.as_sql()) {
+        tracing::warn!(
+            current = ?current,
+            configured = %tokenizer.as_sql(),
+            "search index tokenizer differs from the configuration, run `twilite reindex`",
+        );
+    }
+
+    let reader = PoolBuilder::new()
+        .path(&config.database.path)
+        .journal_mode(JournalMode::Wal)
+        .num_conns(config.database.read_connections.max(1))
+        .open()
+        .await
+        .with_context(|| format!("unable to initialize database: {}", &config.database.path))?;
+
+    Server::start(config, reader, writer, args.open).await
 }
 
 #[tokio::main]
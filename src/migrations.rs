@@ -1,6 +1,6 @@
 //! Run database migrations.
 use anyhow::Result;
-use async_sqlite::{Client, rusqlite::Connection};
+use async_sqlite::{Pool, rusqlite::Connection};
 use refinery::Report;
 use tokio::sync::oneshot;
 
@@ -10,7 +10,9 @@ mod embedded {
 }
 
 /// Run migrations for a connection.
-fn migrate_connection(conn: &mut Connection) -> std::result::Result<Report, refinery::Error> {
+pub(crate) fn migrate_connection(
+    conn: &mut Connection,
+) -> std::result::Result<Report, refinery::Error> {
     tracing::debug!("migration::started");
     let report = embedded::migrations::runner().run(conn)?;
     let applied = report.applied_migrations();
@@ -27,15 +29,26 @@ fn migrate_connection(conn: &mut Connection) -> std::result::Result<Report, refi
     Ok(report)
 }
 
-/// Run migrations for a client.
-pub async fn migrate_client(client: &mut Client) -> Result<Report> {
+/// Migrated in-memory database on a single connection.
+#[cfg(test)]
+pub(crate) async fn test_pool() -> Pool {
+    let pool = async_sqlite::PoolBuilder::new()
+        .num_conns(1)
+        .open()
+        .await
+        .unwrap();
+    migrate_pool(&pool).await.unwrap();
+    pool
+}
+
+/// Run migrations for a pool.
+pub async fn migrate_pool(pool: &Pool) -> Result<Report> {
     let (tx, rx) = oneshot::channel::<std::result::Result<Report, refinery::Error>>();
-    client
-        .conn_mut(|conn| {
-            let result = migrate_connection(conn);
-            tx.send(result).unwrap();
-            Ok(())
-        })
-        .await?;
+    pool.conn_mut(|conn| {
+        let result = migrate_connection(conn);
+        tx.send(result).unwrap();
+        Ok(())
+    })
+    .await?;
     Ok(rx.await.unwrap()?)
 }
@@ -0,0 +1,248 @@
+//! Search query language.
+//!
+//! Terms are matched with AND by default:
+//!
+//! * `word` matches pages containing the word, `word*` is a prefix match
+//! * `"exact phrase"` matches the words in order
+//! * `-word` or `NOT word` excludes pages containing the word
+//! * `a OR b` matches either term
+//! * `title:word` only matches the page name
+//! * `tag:name` only matches pages with the tag, `-tag:name` excludes them
+//!
+//! Every term is quoted when compiled to an FTS5 expression so
+//! FTS5 syntax characters in the input are always matched literally.
+
+/// Maximum number of terms compiled from a query.
+const MAX_TERMS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Any,
+    Title,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    text: String,
+    field: Field,
+    prefix: bool,
+}
+
+impl Term {
+    fn to_fts(&self, title_column: &str) -> String {
+        let mut expr = format!("\"{}\"", self.text.replace('"', "\"\""));
+        if self.prefix {
+            expr.push_str(" *");
+        }
+        match self.field {
+            Field::Any => expr,
+            Field::Title => format!("{} : {}", title_column, expr),
+        }
+    }
+}
+
+/// Parsed search query.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchTerms {
+    /// Groups of alternatives, every group must match.
+    include: Vec<Vec<Term>>,
+    /// Terms that must not match.
+    exclude: Vec<Term>,
+    /// Tags that pages must have.
+    pub tags: Vec<String>,
+    /// Tags that pages must not have.
+    pub exclude_tags: Vec<String>,
+}
+
+/// Split the input into words and quoted phrases, a `-` or field
+/// prefix may precede a phrase as in `title:"exact phrase"`.
+fn tokenize(input: &str) -> Vec<(String, Option<String>)> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let read_phrase = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let mut phrase = String::new();
+        for c in chars.by_ref() {
+            if c == '"' {
+                break;
+            }
+            phrase.push(c);
+        }
+        phrase
+    };
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else {
+            let mut word = String::new();
+            let mut phrase = None;
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                chars.next();
+                if c == '"' && (word.is_empty() || word == "-" || word.ends_with(':')) {
+                    phrase = Some(read_phrase(&mut chars));
+                    break;
+                }
+                word.push(c);
+            }
+            tokens.push((word, phrase));
+        }
+    }
+    tokens
+}
+
+impl SearchTerms {
+    pub fn parse(input: &str) -> Self {
+        let mut terms = Self::default();
+        let mut negate = false;
+        let mut alternative = false;
+        let mut count = 0;
+
+        for (text, phrase) in tokenize(input) {
+            if phrase.is_none() {
+                match text.as_str() {
+                    "AND" => continue,
+                    "OR" => {
+                        alternative = true;
+                        continue;
+                    }
+                    "NOT" => {
+                        negate = true;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            let (text, negated) = match text.strip_prefix('-') {
+                Some(rest) => (rest.to_owned(), !rest.is_empty() || phrase.is_some()),
+                None => (text, false),
+            };
+            let negated = negated || std::mem::take(&mut negate);
+
+            // a field prefix applies to the rest of the word or the phrase
+            let (field, text) = match text.split_once(':') {
+                Some(("title", rest)) => (Some(Field::Title), rest.to_owned()),
+                Some(("tag", rest)) => (None, rest.to_owned()),
+                _ => (Some(Field::Any), text),
+            };
+            let (text, prefix) = match phrase {
+                Some(phrase) => (phrase, false),
+                None => match text.strip_suffix('*') {
+                    Some(stem) => (stem.trim_end_matches('*').to_owned(), true),
+                    None => (text, false),
+                },
+            };
+            let text = text.trim().to_owned();
+            if text.is_empty() || count >= MAX_TERMS {
+                alternative = false;
+                continue;
+            }
+            count += 1;
+
+            let Some(field) = field else {
+                if negated {
+                    terms.exclude_tags.push(text);
+                } else {
+                    terms.tags.push(text);
+                }
+                alternative = false;
+                continue;
+            };
+
+            let term = Term {
+                text,
+                field,
+                prefix,
+            };
+            if negated {
+                terms.exclude.push(term);
+            } else if std::mem::take(&mut alternative) && !terms.include.is_empty() {
+                terms.include.last_mut().unwrap().push(term);
+            } else {
+                terms.include.push(vec![term]);
+            }
+        }
+        terms
+    }
+
+    /// Compile to an FTS5 MATCH expression with `title:` terms matching
+    /// `title_column`, `None` when nothing would match because there are
+    /// no terms to include.
+    pub fn to_fts(&self, title_column: &str) -> Option<String> {
+        if self.include.is_empty() {
+            return None;
+        }
+
+        let group = |terms: &[Term]| {
+            let alternatives: Vec<String> =
+                terms.iter().map(|term| term.to_fts(title_column)).collect();
+            if alternatives.len() == 1 {
+                alternatives.into_iter().next().unwrap()
+            } else {
+                format!("({})", alternatives.join(" OR "))
+            }
+        };
+        let include: Vec<String> = self.include.iter().map(|terms| group(terms)).collect();
+        let mut expr = include.join(" AND ");
+        if !self.exclude.is_empty() {
+            let exclude: Vec<String> = self
+                .exclude
+                .iter()
+                .map(|term| term.to_fts(title_column))
+                .collect();
+            expr = format!("({}) NOT ({})", expr, exclude.join(" OR "));
+        }
+        Some(expr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SearchTerms;
+
+    fn compile(input: &str) -> Option<String> {
+        SearchTerms::parse(input).to_fts("page_name")
+    }
+
+    #[test]
+    fn words_and_phrases() {
+        assert_eq!(compile("foo bar"), Some(r#""foo" AND "bar""#.to_owned()));
+        assert_eq!(
+            compile(r#""exact phrase" foo*"#),
+            Some(r#""exact phrase" AND "foo" *"#.to_owned())
+        );
+        assert_eq!(
+            compile("foo OR bar baz"),
+            Some(r#"("foo" OR "bar") AND "baz""#.to_owned())
+        );
+    }
+
+    #[test]
+    fn exclusions_and_fields() {
+        assert_eq!(
+            compile(r#"foo -bar NOT "a b" title:"Run book""#),
+            Some(r#"("foo" AND page_name : "Run book") NOT ("bar" OR "a b")"#.to_owned())
+        );
+        assert_eq!(compile("-bar"), None);
+
+        let terms = SearchTerms::parse("tag:ops -tag:draft title:oncall");
+        assert_eq!(terms.tags, vec!["ops".to_owned()]);
+        assert_eq!(terms.exclude_tags, vec!["draft".to_owned()]);
+        assert_eq!(
+            terms.to_fts("page_name"),
+            Some(r#"page_name : "oncall""#.to_owned())
+        );
+    }
+
+    #[test]
+    fn syntax_is_quoted() {
+        assert_eq!(
+            compile(r#"a"b (c) NEAR ^d"#),
+            Some(r#""a""b" AND "(c)" AND "NEAR" AND "^d""#.to_owned())
+        );
+        assert_eq!(compile("* - OR"), None);
+    }
+}
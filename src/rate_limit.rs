@@ -0,0 +1,247 @@
+//! Token bucket rate limits.
+//!
+//! Each class of request has a bucket per caller: signed in users have
+//! their own, so a team behind one NAT or proxy doesn't share a budget,
+//! and anonymous requests use the bucket of their client address.
+//! Requests without credentials are charged to their address before
+//! authentication, requests with credentials are charged after to the
+//! user, or to the address when the credentials don't resolve to one.
+//! Buckets refill at a steady rate up to their burst size and requests
+//! are rejected with `429 Too Many Requests` while a bucket is empty.
+use crate::{
+    auth::{Identity, SESSION_COOKIE, bearer_token, is_write},
+    config::{Limit, RateLimit},
+    error::ServerError,
+    server::ServerState,
+};
+use axum::{
+    Extension,
+    extract::{ConnectInfo, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Instant,
+};
+use tokio::sync::Mutex;
+
+/// Class of request sharing a bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitClass {
+    Write,
+    Search,
+    Login,
+}
+
+impl LimitClass {
+    fn classify(method: &Method, path: &str) -> Option<Self> {
+        // running a saved search costs as much as a search
+        let search =
+            path == "/api/search" || (!is_write(method) && path.starts_with("/api/searches/"));
+        if path.starts_with("/login/") || path == "/api/github/callback" {
+            Some(Self::Login)
+        } else if search {
+            Some(Self::Search)
+        } else if is_write(method) {
+            Some(Self::Write)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Write => "write",
+            Self::Search => "search",
+            Self::Login => "login",
+        }
+    }
+}
+
+/// Caller a bucket belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Caller {
+    User(i32),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn new(limit: Limit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, limit: Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second()).min(limit.burst as f64);
+        self.updated_at = now;
+    }
+
+    /// Take a token, or return the seconds until one is available.
+    fn take(&mut self, limit: Limit, now: Instant) -> Result<(), u64> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if limit.per_minute == 0 {
+            Err(60)
+        } else {
+            Err(((1.0 - self.tokens) / limit.per_second()).ceil() as u64)
+        }
+    }
+
+    fn is_full(&self, limit: Limit) -> bool {
+        self.tokens >= limit.burst as f64
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(LimitClass, Caller), Bucket>>,
+}
+
+impl RateLimiter {
+    async fn take(&self, class: LimitClass, caller: Caller, limit: Limit) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().await;
+        buckets
+            .entry((class, caller))
+            .or_insert_with(|| Bucket::new(limit, now))
+            .take(limit, now)
+    }
+
+    /// Discard buckets that have refilled, they behave the same as a
+    /// new bucket.
+    pub async fn sweep(&self, limits: &RateLimit) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().await;
+        buckets.retain(|(class, _), bucket| {
+            let limit = limits.get(*class);
+            bucket.refill(limit, now);
+            !bucket.is_full(limit)
+        });
+    }
+}
+
+/// Take a token from the bucket of a caller when the request is limited.
+async fn check(
+    state: &ServerState,
+    method: &Method,
+    path: &str,
+    caller: Caller,
+) -> Result<(), ServerError> {
+    let limits = &state.config.rate_limit;
+    let Some(class) = LimitClass::classify(method, path) else {
+        return Ok(());
+    };
+    if !limits.enabled {
+        return Ok(());
+    }
+
+    let limit = limits.get(class);
+    if let Err(retry_after) = state.rate_limiter.take(class, caller, limit).await {
+        tracing::warn!(
+            class = %class.as_str(),
+            caller = ?caller,
+            path = %path,
+            "rate_limit::rejected",
+        );
+        return Err(ServerError::TooManyRequests(retry_after));
+    }
+    Ok(())
+}
+
+/// Whether a request carries an API token or a session cookie.
+fn has_credentials(request: &Request) -> bool {
+    bearer_token(request).is_some()
+        || CookieJar::from_headers(request.headers())
+            .get(SESSION_COOKIE)
+            .is_some()
+}
+
+/// Apply rate limits to writes, search and sign in per client address
+/// for requests without credentials, before authentication.
+pub async fn limit_ip(
+    Extension(state): Extension<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    if has_credentials(&request) {
+        return Ok(next.run(request).await);
+    }
+    let client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(ip) = client_ip {
+        check(
+            &state,
+            request.method(),
+            request.uri().path(),
+            Caller::Ip(ip),
+        )
+        .await?;
+    }
+    Ok(next.run(request).await)
+}
+
+/// Apply rate limits to writes, search and sign in per signed in user,
+/// or per client address for credentials that resolve to no user.
+pub async fn limit_user(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let caller = match (&identity.user, identity.client_ip) {
+        (Some(user), _) => Some(Caller::User(user.user_id)),
+        // requests without credentials were charged before authentication
+        (None, Some(ip)) if has_credentials(&request) => Some(Caller::Ip(ip)),
+        (None, _) => None,
+    };
+    if let Some(caller) = caller {
+        check(&state, request.method(), request.uri().path(), caller).await?;
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Bucket;
+    use crate::config::Limit;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn bucket_refills() {
+        let limit = Limit {
+            burst: 2,
+            per_minute: 60,
+        };
+        let now = Instant::now();
+        let mut bucket = Bucket::new(limit, now);
+        assert_eq!(bucket.take(limit, now), Ok(()));
+        assert_eq!(bucket.take(limit, now), Ok(()));
+        assert_eq!(bucket.take(limit, now), Err(1));
+
+        let later = now + Duration::from_millis(1500);
+        assert_eq!(bucket.take(limit, later), Ok(()));
+        assert!(bucket.take(limit, later).is_err());
+
+        let much_later = later + Duration::from_secs(60);
+        bucket.refill(limit, much_later);
+        assert!(bucket.is_full(limit));
+    }
+}
@@ -1,11 +1,32 @@
 use crate::{
+    auth::Identity,
     entity::{
+        acl::{Access, AclEntity, AclPrincipal, AclResponse, AclTarget, Permission},
+        audit::{Actor, AuditAction, AuditEntity, AuditQuery, AuditResponse},
+        change::{ChangeEntity, ChangeResponse},
+        contributor::{ContributorEntity, ContributorResponse},
         file::FileEntity,
-        page::{PageEntity, PagePreview, PageResponse, PageSelectOptions, PageUpload},
-        search::{SearchEntity, SearchQuery, SearchRecord},
+        group::{GroupEntity, GroupResponse},
+        page::{
+            PageChild, PageEntity, PageListOptions, PagePreview, PageProperties, PageResponse,
+            PageSelectOptions, PageSort, PageUpload,
+        },
+        saved_search::{SavedSearchEntity, SavedSearchResponse},
+        search::{SearchEntity, SearchQuery},
+        tag::{TagEntity, TagResponse},
+        token::{ApiTokenEntity, ApiTokenResponse, TokenScope},
+        user::{Role, UserEntity, UserResponse},
+        watch::{WatchEntity, WatchResponse},
+        webhook_delivery::{WebhookDeliveryEntity, WebhookDeliveryQuery, WebhookDeliveryResponse},
     },
     error::ServerError,
+    feed::{Feed, FeedEntry, RssFeed},
+    helpers::{
+        decode_cursor, is_valid_page_name, is_valid_timestamp, normalize_tag, trim_preview_text,
+        wiki_path,
+    },
     server::ServerState,
+    webhook::{WebhookEvent, WebhookPayload},
 };
 use axum::{
     Extension, Json,
@@ -14,10 +35,12 @@ use axum::{
     http::{HeaderMap, StatusCode, Uri, header},
     response::{IntoResponse, Redirect, Response},
 };
-use axum_extra::extract::OptionalQuery;
+// accepts repeated keys such as `tag=a&tag=b`
+use axum_extra::extract::{OptionalQuery, Query as RepeatedQuery};
 use rust_embed::RustEmbed;
 use sql_query_builder as sql;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
 use uuid::Uuid;
 
 #[derive(RustEmbed)]
@@ -26,11 +49,12 @@ struct Assets;
 
 pub async fn api_file_content(
     Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
     Path(file_uuid): Path<Uuid>,
 ) -> Result<Response, ServerError> {
-    let client = state.client.lock().await;
+    let pool = &state.reader;
     let (file_size, content_type, content) =
-        FileEntity::find_buffer_by_uuid(&client, file_uuid).await?;
+        FileEntity::find_buffer_by_uuid(pool, file_uuid, identity.access()).await?;
     let response = Response::builder()
         .header(header::CONTENT_TYPE, content_type)
         .header(header::CONTENT_LENGTH, file_size.to_string())
@@ -40,33 +64,86 @@ pub async fn api_file_content(
 
 pub async fn api_search(
     Extension(state): Extension<Arc<ServerState>>,
-    Query(search_query): Query<SearchQuery>,
+    Extension(identity): Extension<Identity>,
+    RepeatedQuery(search_query): RepeatedQuery<SearchQuery>,
 ) -> Result<Response, ServerError> {
-    let client = state.client.lock().await;
-    let results = SearchEntity::fts_search(&client, search_query).await?;
-    let results = results
-        .into_iter()
-        .map(SearchRecord::from)
-        .collect::<Vec<_>>();
-    Ok(Json(results).into_response())
+    let pool = &state.reader;
+    let response = SearchEntity::fts_search(pool, search_query, identity.access()).await?;
+    Ok(Json(response).into_response())
 }
 
 pub async fn api_delete_page(
     Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
     Path(page_uuid): Path<Uuid>,
 ) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    // the access entries of the page go with it, so whether webhooks
+    // may hear of the deletion is decided before
+    let public = Access::default()
+        .read_condition("pages")
+        .unwrap_or_else(|| "1".to_owned());
+    let select = sql::Select::new()
+        .select(&format!("page_name, {} AS public", public))
+        .from("pages")
+        .where_clause("page_uuid = ?1");
+    let acls = sql::Delete::new()
+        .delete_from("page_acls")
+        .where_clause("page_id = (SELECT page_id FROM pages WHERE page_uuid = ?1)");
+    let contributors = sql::Delete::new()
+        .delete_from("page_contributors")
+        .where_clause("page_id = (SELECT page_id FROM pages WHERE page_uuid = ?1)");
+    let links = sql::Delete::new()
+        .delete_from("page_links")
+        .where_clause("page_id = (SELECT page_id FROM pages WHERE page_uuid = ?1)");
+    let tags = sql::Delete::new()
+        .delete_from("page_tags")
+        .where_clause("page_id = (SELECT page_id FROM pages WHERE page_uuid = ?1)");
+    let properties = sql::Delete::new()
+        .delete_from("page_properties")
+        .where_clause("page_id = (SELECT page_id FROM pages WHERE page_uuid = ?1)");
     let query = sql::Delete::new()
         .delete_from("pages")
         .where_clause("page_uuid = ?1");
-    let client = state.client.lock().await;
-    let content: Result<usize, async_sqlite::Error> = client
-        .conn(move |conn| {
-            let mut stmt = conn.prepare_cached(&query.as_string())?;
-            stmt.execute([page_uuid.to_string()])
+    let pool = &state.writer;
+    let actor = Actor::from(&identity);
+    let content: Result<(String, bool), async_sqlite::Error> = pool
+        .conn_mut(move |conn| {
+            let tx = conn.transaction()?;
+            let (page_name, public): (String, bool) = tx
+                .prepare_cached(&select.as_string())?
+                .query_row([page_uuid.to_string()], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+            AuditEntity::record(
+                &tx,
+                &actor,
+                AuditAction::PageDelete,
+                &page_uuid.to_string(),
+                Some(&page_name),
+            )?;
+            tx.execute(&acls.as_string(), [page_uuid.to_string()])?;
+            tx.execute(&contributors.as_string(), [page_uuid.to_string()])?;
+            tx.execute(&links.as_string(), [page_uuid.to_string()])?;
+            tx.execute(&tags.as_string(), [page_uuid.to_string()])?;
+            tx.execute(&properties.as_string(), [page_uuid.to_string()])?;
+            tx.execute(&query.as_string(), [page_uuid.to_string()])?;
+            tx.commit()?;
+            Ok((page_name, public))
         })
         .await;
     match content {
-        Ok(_affected_rows) => Ok(StatusCode::OK.into_response()),
+        Ok((page_name, public)) => {
+            if public {
+                state.webhooks.emit(WebhookPayload::new(
+                    WebhookEvent::PageDeleted,
+                    &identity,
+                    page_uuid,
+                    page_name,
+                ));
+            }
+            Ok(StatusCode::OK.into_response())
+        }
         Err(async_sqlite::Error::Rusqlite(async_sqlite::rusqlite::Error::QueryReturnedNoRows)) => {
             Ok(StatusCode::NOT_FOUND.into_response())
         }
@@ -76,13 +153,14 @@ pub async fn api_delete_page(
 
 pub async fn api_select_page_content(
     Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
     headers: HeaderMap,
     Path(page_name): Path<String>,
     options: OptionalQuery<PageSelectOptions>,
 ) -> Result<Response, ServerError> {
     if let Some(accept) = headers.get("accept") {
         if accept == "application/json" {
-            api_select_page_json(state, page_name, options.0.unwrap_or_default()).await
+            api_select_page_json(state, identity, page_name, options.0.unwrap_or_default()).await
         } else {
             Ok((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported media type").into_response())
         }
@@ -91,11 +169,71 @@ pub async fn api_select_page_content(
     }
 }
 
+/// Pages listed at once by default and at most.
+const PAGE_LIST_LIMIT: usize = 50;
+const MAX_PAGE_LIST_LIMIT: usize = 200;
+
+/// List pages sorted by `sort` (`name`, `created` or `updated`) in
+/// `order` (`asc` or `desc`), starting after `cursor` from a previous
+/// listing. `prefix` filters by name, `preview=true` adds preview text
+/// and `prop.<key>=<value>` parameters only keep pages with the
+/// property value.
+pub async fn api_pages(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ServerError> {
+    let mut options = PageListOptions {
+        sort: PageSort::default(),
+        descending: false,
+        prefix: None,
+        properties: PageProperties::new(),
+        cursor: None,
+        limit: PAGE_LIST_LIMIT,
+        include_preview: false,
+    };
+    let mut order = None;
+    for (name, value) in params {
+        match name.as_str() {
+            "sort" => options.sort = PageSort::from_param(&value).ok_or(ServerError::BadRequest)?,
+            "order" => order = Some(value),
+            "prefix" => options.prefix = Some(value).filter(|prefix| !prefix.is_empty()),
+            "cursor" => {
+                options.cursor = Some(decode_cursor(&value).ok_or(ServerError::BadRequest)?)
+            }
+            "limit" => {
+                let limit: usize = value.parse().map_err(|_| ServerError::BadRequest)?;
+                options.limit = limit.clamp(1, MAX_PAGE_LIST_LIMIT);
+            }
+            "preview" => options.include_preview = value == "true",
+            _ => {
+                if let Some(key) = name.strip_prefix("prop.") {
+                    if !is_property_key(key) {
+                        return Err(ServerError::BadRequest);
+                    }
+                    options.properties.insert(key.to_owned(), value);
+                }
+            }
+        }
+    }
+    // names read best from A to Z, dates from the latest
+    options.descending = match order.as_deref() {
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(_) => return Err(ServerError::BadRequest),
+        None => options.sort != PageSort::Name,
+    };
+    let pool = &state.reader;
+    let response = PageEntity::find_all(pool, options, identity.access()).await?;
+    Ok(Json(response).into_response())
+}
+
 pub async fn api_recent_pages(
     Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
 ) -> Result<Response, ServerError> {
-    let client = state.client.lock().await;
-    match PageEntity::find_recent(&client).await {
+    let pool = &state.reader;
+    match PageEntity::find_recent(pool, 10, identity.access()).await {
         Ok(entities) => {
             let response: Vec<PagePreview> = entities.into_iter().map(PagePreview::from).collect();
             Ok(Json(response).into_response())
@@ -104,13 +242,61 @@ pub async fn api_recent_pages(
     }
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct SuggestQuery {
+    #[serde(default)]
+    pub prefix: String,
+    pub limit: Option<usize>,
+}
+
+pub async fn api_suggest_pages(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Query(query): Query<SuggestQuery>,
+) -> Result<Response, ServerError> {
+    if query.prefix.trim().is_empty() {
+        return Err(ServerError::BadRequest);
+    }
+    let pool = &state.reader;
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let suggestions = PageEntity::suggest(pool, query.prefix, limit, identity.access()).await?;
+    Ok(Json(suggestions).into_response())
+}
+
+pub async fn api_page_contributors(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(page_name): Path<String>,
+) -> Result<Response, ServerError> {
+    let pool = &state.reader;
+    let contributors =
+        ContributorEntity::find_by_page_name(pool, page_name, identity.access()).await?;
+    let response: Vec<ContributorResponse> = contributors
+        .into_iter()
+        .map(ContributorResponse::from)
+        .collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_page_children(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(page_name): Path<String>,
+) -> Result<Response, ServerError> {
+    let pool = &state.reader;
+    let response: Vec<PageChild> =
+        PageEntity::find_children(pool, page_name, identity.access()).await?;
+    Ok(Json(response).into_response())
+}
+
 async fn api_select_page_json(
     state: Arc<ServerState>,
+    identity: Identity,
     page_name: String,
     options: PageSelectOptions,
 ) -> Result<Response, ServerError> {
-    let client = state.client.lock().await;
-    match PageEntity::find_by_name(&client, page_name, options).await {
+    let pool = &state.reader;
+    match PageEntity::find_by_name(pool, page_name, options, identity.access()).await {
         Ok(entity) => {
             let response: PageResponse = entity.into();
             Ok(Json(response).into_response())
@@ -119,18 +305,80 @@ async fn api_select_page_json(
     }
 }
 
+/// Normalize tags sent as repeated or comma separated values.
+fn parse_tags(values: Vec<String>) -> Result<Vec<String>, ServerError> {
+    let mut tags: Vec<String> = Vec::new();
+    for value in values.iter().flat_map(|value| value.split(',')) {
+        if value.trim().is_empty() {
+            continue;
+        }
+        let tag = normalize_tag(value).ok_or(ServerError::BadRequest)?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    Ok(tags)
+}
+
+/// Maximum number of properties of a page.
+const MAX_PROPERTIES: usize = 32;
+/// Maximum length of a property value in characters.
+const MAX_PROPERTY_LENGTH: usize = 512;
+
+/// Whether a property key is lowercase letters, digits, `_` and `-`
+/// starting with a letter.
+fn is_property_key(key: &str) -> bool {
+    key.len() <= 32
+        && key.starts_with(|c: char| c.is_ascii_lowercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// Parse properties sent as a JSON object of strings, empty values
+/// are left out.
+fn parse_properties(json: &str) -> Result<PageProperties, ServerError> {
+    let values: HashMap<String, String> =
+        serde_json::from_str(json).map_err(|_| ServerError::BadRequest)?;
+    let mut properties = PageProperties::new();
+    for (key, value) in values {
+        let value = value.trim();
+        if !is_property_key(&key) || value.chars().count() > MAX_PROPERTY_LENGTH {
+            return Err(ServerError::BadRequest);
+        }
+        if !value.is_empty() {
+            properties.insert(key, value.to_owned());
+        }
+    }
+    if properties.len() > MAX_PROPERTIES {
+        return Err(ServerError::BadRequest);
+    }
+    Ok(properties)
+}
+
 pub async fn api_insert_page(
     Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
     mut multipart: Multipart,
 ) -> Result<Response, ServerError> {
+    identity.require(Role::Editor)?;
     let mut page_name = None;
     let mut page_content = None;
+    let mut page_tags: Option<Vec<String>> = None;
+    let mut page_properties = None;
     let mut uploads: Vec<(Option<String>, Option<String>, Bytes)> = vec![];
 
     while let Some(field) = multipart.next_field().await.unwrap() {
         match field.name().unwrap() {
             "pageName" => page_name = Some(field.text().await.unwrap()),
             "pageContent" => page_content = Some(field.text().await.unwrap()),
+            "pageTags" => {
+                let tag = field.text().await.map_err(|_| ServerError::BadRequest)?;
+                page_tags.get_or_insert_default().push(tag)
+            }
+            "pageProperties" => {
+                page_properties = Some(field.text().await.map_err(|_| ServerError::BadRequest)?)
+            }
             "uploads" => {
                 uploads.push((
                     field.file_name().map(|s| s.to_owned()),
@@ -145,6 +393,9 @@ pub async fn api_insert_page(
     let (Some(page_name), Some(page_content)) = (page_name, page_content) else {
         return Ok(StatusCode::BAD_REQUEST.into_response());
     };
+    if !is_valid_page_name(&page_name) {
+        return Err(ServerError::BadRequest);
+    }
 
     let uploads = uploads
         .into_iter()
@@ -152,27 +403,77 @@ pub async fn api_insert_page(
         .map(|u| PageUpload(u.0.unwrap(), u.1.unwrap(), u.2))
         .collect::<Vec<_>>();
 
-    let client = state.client.lock().await;
+    let pool = &state.writer;
 
-    match PageEntity::add(&client, page_name, page_content, uploads).await {
-        Ok(_) => Ok(StatusCode::OK.into_response()),
+    if !AclEntity::can_edit(pool, None, page_name.clone(), identity.access()).await? {
+        return Err(ServerError::Forbidden);
+    }
+
+    let page_tags = parse_tags(page_tags.unwrap_or_default())?;
+    let page_properties = page_properties
+        .map(|json| parse_properties(&json))
+        .transpose()?
+        .unwrap_or_default();
+    let file_names: Vec<String> = uploads.iter().map(|u| u.0.clone()).collect();
+    match PageEntity::add(
+        pool,
+        &identity,
+        page_name.clone(),
+        page_content,
+        page_tags,
+        page_properties,
+        uploads,
+    )
+    .await
+    {
+        Ok(page_uuid) => {
+            state.webhooks.emit(WebhookPayload::new(
+                WebhookEvent::PageCreated,
+                &identity,
+                page_uuid,
+                page_name.clone(),
+            ));
+            for file_name in file_names {
+                state.webhooks.emit(WebhookPayload {
+                    file_name: Some(file_name),
+                    ..WebhookPayload::new(
+                        WebhookEvent::FileUploaded,
+                        &identity,
+                        page_uuid,
+                        page_name.clone(),
+                    )
+                });
+            }
+            Ok(StatusCode::OK.into_response())
+        }
         Err(e) => Err(e),
     }
 }
 
 pub async fn api_update_page(
     Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
     Path(page_uuid): Path<Uuid>,
     mut multipart: Multipart,
 ) -> Result<Response, ServerError> {
+    identity.require(Role::Editor)?;
     let mut page_name = None;
     let mut page_content = None;
+    let mut page_tags: Option<Vec<String>> = None;
+    let mut page_properties = None;
     let mut uploads: Vec<(Option<String>, Option<String>, Bytes)> = vec![];
 
     while let Some(field) = multipart.next_field().await.unwrap() {
         match field.name().unwrap() {
             "pageName" => page_name = Some(field.text().await.unwrap()),
             "pageContent" => page_content = Some(field.text().await.unwrap()),
+            "pageTags" => {
+                let tag = field.text().await.map_err(|_| ServerError::BadRequest)?;
+                page_tags.get_or_insert_default().push(tag)
+            }
+            "pageProperties" => {
+                page_properties = Some(field.text().await.map_err(|_| ServerError::BadRequest)?)
+            }
             "uploads" => {
                 uploads.push((
                     field.file_name().map(|s| s.to_owned()),
@@ -187,6 +488,9 @@ pub async fn api_update_page(
     let (Some(page_name), Some(page_content)) = (page_name, page_content) else {
         return Ok(StatusCode::BAD_REQUEST.into_response());
     };
+    if !is_valid_page_name(&page_name) {
+        return Err(ServerError::BadRequest);
+    }
 
     let uploads = uploads
         .into_iter()
@@ -194,11 +498,684 @@ pub async fn api_update_page(
         .map(|u| PageUpload(u.0.unwrap(), u.1.unwrap(), u.2))
         .collect::<Vec<_>>();
 
-    let client = state.client.lock().await;
+    let pool = &state.writer;
 
-    PageEntity::edit(&client, page_uuid, page_name, page_content, uploads)
-        .await
-        .map(|_| StatusCode::OK.into_response())
+    if !AclEntity::can_edit(pool, Some(page_uuid), page_name.clone(), identity.access()).await? {
+        return Err(ServerError::Forbidden);
+    }
+
+    // tags and properties are left unchanged when the field is not sent
+    let page_tags = page_tags.map(parse_tags).transpose()?;
+    let page_properties = page_properties
+        .map(|json| parse_properties(&json))
+        .transpose()?;
+    let file_names: Vec<String> = uploads.iter().map(|u| u.0.clone()).collect();
+    let previous_name = PageEntity::edit(
+        pool,
+        &identity,
+        page_uuid,
+        page_name.clone(),
+        page_content,
+        page_tags,
+        page_properties,
+        uploads,
+    )
+    .await?;
+    state.webhooks.emit(WebhookPayload::new(
+        WebhookEvent::PageUpdated,
+        &identity,
+        page_uuid,
+        page_name.clone(),
+    ));
+    if previous_name != page_name {
+        state.webhooks.emit(WebhookPayload {
+            previous_name: Some(previous_name),
+            ..WebhookPayload::new(
+                WebhookEvent::PageRenamed,
+                &identity,
+                page_uuid,
+                page_name.clone(),
+            )
+        });
+    }
+    for file_name in file_names {
+        state.webhooks.emit(WebhookPayload {
+            file_name: Some(file_name),
+            ..WebhookPayload::new(
+                WebhookEvent::FileUploaded,
+                &identity,
+                page_uuid,
+                page_name.clone(),
+            )
+        });
+    }
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn api_tags(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    let pool = &state.reader;
+    let tags = TagEntity::find_all(pool, identity.access()).await?;
+    let response: Vec<TagResponse> = tags.into_iter().map(TagResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_tag_pages(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(tag_name): Path<String>,
+) -> Result<Response, ServerError> {
+    let tag_name = normalize_tag(&tag_name).ok_or(ServerError::BadRequest)?;
+    let pool = &state.reader;
+    let pages = PageEntity::find_by_tag(pool, tag_name, identity.access()).await?;
+    let response: Vec<PagePreview> = pages.into_iter().map(PagePreview::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_current_user(
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?.clone();
+    Ok(Json(UserResponse::from(user)).into_response())
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRequest {
+    pub token_name: String,
+    pub token_scope: TokenScope,
+}
+
+pub async fn api_tokens(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.reader;
+    let tokens = ApiTokenEntity::find_all_by_user_id(pool, user.user_id).await?;
+    let response: Vec<ApiTokenResponse> = tokens.into_iter().map(ApiTokenResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_create_token(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Json(request): Json<TokenRequest>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_session()?;
+    // tokens may not grant more than the caller currently has
+    identity.require(request.token_scope.role())?;
+    let token_name = request.token_name.trim().to_owned();
+    if token_name.is_empty() {
+        return Err(ServerError::BadRequest);
+    }
+    let pool = &state.writer;
+    let response =
+        ApiTokenEntity::create(pool, &identity, user, token_name, request.token_scope).await?;
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_delete_token(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(token_uuid): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_session()?;
+    let pool = &state.writer;
+    ApiTokenEntity::delete(pool, &identity, user, token_uuid).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Maximum number of entries in a feed.
+const FEED_LIMIT: u32 = 50;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchRequest {
+    pub search_name: String,
+    pub search_query: String,
+}
+
+/// Paging and filters of a saved search, as for `/api/search`.
+#[derive(Debug, serde::Deserialize)]
+pub struct SavedSearchQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub tag: Vec<String>,
+    pub editor: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+pub async fn api_saved_searches(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.reader;
+    let searches = SavedSearchEntity::find_all_by_user_id(pool, user.user_id).await?;
+    let response: Vec<SavedSearchResponse> = searches
+        .into_iter()
+        .map(SavedSearchResponse::from)
+        .collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_create_saved_search(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Json(request): Json<SavedSearchRequest>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let search_name = request.search_name.trim().to_owned();
+    let search_query = request.search_query.trim().to_owned();
+    // the name is used in paths so it may not contain a slash
+    if search_name.is_empty() || search_name.contains('/') || search_query.is_empty() {
+        return Err(ServerError::BadRequest);
+    }
+    let pool = &state.writer;
+    let search = SavedSearchEntity::create(pool, user.user_id, search_name, search_query).await?;
+    Ok(Json(SavedSearchResponse::from(search)).into_response())
+}
+
+/// Run a saved search.
+pub async fn api_run_saved_search(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(search_name): Path<String>,
+    RepeatedQuery(query): RepeatedQuery<SavedSearchQuery>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.reader;
+    let search = SavedSearchEntity::find_by_name(pool, user.user_id, search_name).await?;
+    let search_query = SearchQuery {
+        keywords: search.search_query,
+        limit: query.limit,
+        offset: query.offset,
+        cursor: query.cursor,
+        tag: query.tag,
+        editor: query.editor,
+        since: query.since,
+        until: query.until,
+    };
+    let response = SearchEntity::fts_search(pool, search_query, identity.access()).await?;
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_delete_saved_search(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(search_name): Path<String>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.writer;
+    SavedSearchEntity::delete(pool, user.user_id, search_name).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Address of the wiki for absolute links, the configured public URL
+/// or else the host the request was sent to.
+fn base_url(state: &ServerState, headers: &HeaderMap) -> String {
+    if let Some(public_url) = &state.config.public_url {
+        return public_url.trim_end_matches('/').to_owned();
+    }
+    let scheme = if state.config.auth.secure_cookie {
+        "https"
+    } else {
+        "http"
+    };
+    match headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+    {
+        Some(host) => format!("{}://{}", scheme, host),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SavedSearchFeedQuery {
+    /// Only pages updated after this RFC3339 timestamp.
+    pub since: Option<String>,
+}
+
+/// Atom feed of the most recently created or updated pages matching
+/// a saved search. Fetching the feed doesn't change it, readers tell
+/// new entries apart by their ids.
+pub async fn api_saved_search_feed(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    headers: HeaderMap,
+    Path(search_name): Path<String>,
+    Query(query): Query<SavedSearchFeedQuery>,
+) -> Result<Response, ServerError> {
+    if let Some(since) = &query.since
+        && !is_valid_timestamp(since)
+    {
+        return Err(ServerError::BadRequest);
+    }
+    let base_url = base_url(&state, &headers);
+    let user = identity.require_user()?;
+    let search = SavedSearchEntity::find_by_name(&state.reader, user.user_id, search_name).await?;
+    let pages = SearchEntity::find_matching_pages(
+        &state.reader,
+        &search.search_query,
+        identity.access(),
+        query.since,
+        FEED_LIMIT,
+    )
+    .await?;
+
+    let updated = match pages.first() {
+        Some(page) => page.updated_at.clone(),
+        None => UtcDateTime::now().format(&Rfc3339)?,
+    };
+    let feed = Feed {
+        id: format!("urn:uuid:{}", search.search_uuid),
+        title: format!("{}: {}", search.search_name, search.search_query),
+        link: format!("{}/", base_url),
+        updated,
+        entries: pages
+            .into_iter()
+            .map(|page| FeedEntry {
+                // the update time makes each change a new entry
+                id: format!("urn:uuid:{}#{}", page.page_uuid, page.updated_at),
+                link: format!("{}{}", base_url, wiki_path(&page.page_name)),
+                title: page.page_name,
+                updated: page.updated_at,
+                author: page.updated_by_login,
+                summary: page.summary,
+            })
+            .collect(),
+    };
+    Ok(feed.into_response())
+}
+
+/// Feed of the most recently changed pages.
+async fn recent_feed(
+    state: &ServerState,
+    identity: &Identity,
+    headers: &HeaderMap,
+) -> Result<Feed, ServerError> {
+    let base_url = base_url(state, headers);
+    let pages = PageEntity::find_recent(&state.reader, FEED_LIMIT, identity.access()).await?;
+    let updated = match pages.first() {
+        Some(page) => page.updated_at.clone(),
+        None => UtcDateTime::now().format(&Rfc3339)?,
+    };
+    Ok(Feed {
+        id: "urn:twilite:recent".to_owned(),
+        title: "Recent changes".to_owned(),
+        link: format!("{}/", base_url),
+        updated,
+        entries: pages
+            .into_iter()
+            .map(|page| FeedEntry {
+                // the update time makes each change a new entry
+                id: format!("urn:uuid:{}#{}", page.page_uuid, page.updated_at),
+                link: format!("{}{}", base_url, wiki_path(&page.page_name)),
+                title: page.page_name,
+                author: page
+                    .updated_by
+                    .as_ref()
+                    .map(|author| author.user_login().to_owned()),
+                summary: Some(trim_preview_text(&page.page_text).to_owned()),
+                updated: page.updated_at,
+            })
+            .collect(),
+    })
+}
+
+pub async fn recent_atom_feed(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    headers: HeaderMap,
+) -> Result<Response, ServerError> {
+    let feed = recent_feed(&state, &identity, &headers).await?;
+    Ok(feed.into_response())
+}
+
+pub async fn recent_rss_feed(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    headers: HeaderMap,
+) -> Result<Response, ServerError> {
+    let feed = recent_feed(&state, &identity, &headers).await?;
+    Ok(RssFeed(feed).into_response())
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchRequest {
+    pub page_name: String,
+    /// Also watch the pages below the page.
+    #[serde(default)]
+    pub include_subpages: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ChangesQuery {
+    /// Only changes after this RFC3339 timestamp.
+    pub since: Option<String>,
+    pub limit: Option<u32>,
+}
+
+pub async fn api_watches(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.reader;
+    let watches = WatchEntity::find_all_by_user_id(pool, user.user_id).await?;
+    let response: Vec<WatchResponse> = watches.into_iter().map(WatchResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+/// Watch a page, the page does not need to exist yet.
+pub async fn api_create_watch(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Json(request): Json<WatchRequest>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    if !is_valid_page_name(&request.page_name) {
+        return Err(ServerError::BadRequest);
+    }
+    let pool = &state.writer;
+    let watch = WatchEntity::watch(
+        pool,
+        user.user_id,
+        request.page_name,
+        request.include_subpages,
+    )
+    .await?;
+    Ok(Json(WatchResponse::from(watch)).into_response())
+}
+
+pub async fn api_delete_watch(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(page_name): Path<String>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.writer;
+    WatchEntity::delete(pool, user.user_id, page_name).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Changes to the pages the user watches, latest first.
+pub async fn api_watched_changes(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Response, ServerError> {
+    let user = identity.require_user()?;
+    let pool = &state.reader;
+    let limit = query.limit.unwrap_or(FEED_LIMIT).clamp(1, 200);
+    let changes =
+        ChangeEntity::find_watched(pool, user.user_id, query.since, limit, identity.access())
+            .await?;
+    let response: Vec<ChangeResponse> = changes.into_iter().map(ChangeResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+/// Atom feed of the latest changes to a page.
+pub async fn api_page_feed(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    headers: HeaderMap,
+    Path(page_name): Path<String>,
+) -> Result<Response, ServerError> {
+    let base_url = base_url(&state, &headers);
+    let changes = ChangeEntity::find_by_page_name(
+        &state.reader,
+        page_name.clone(),
+        FEED_LIMIT,
+        identity.access(),
+    )
+    .await?;
+
+    let updated = match changes.first() {
+        Some(change) => change.changed_at.clone(),
+        None => UtcDateTime::now().format(&Rfc3339)?,
+    };
+    let link = format!("{}{}", base_url, wiki_path(&page_name));
+    let feed = Feed {
+        id: format!(
+            "urn:twilite:page:{}",
+            &wiki_path(&page_name)["/wiki/".len()..]
+        ),
+        title: format!("Changes to {}", page_name),
+        link: link.clone(),
+        updated,
+        entries: changes
+            .into_iter()
+            .map(|change| {
+                let verb = match change.change_action.as_str() {
+                    "page.create" => "created",
+                    "page.rename" => "renamed",
+                    "file.upload" => "file uploaded",
+                    _ => "updated",
+                };
+                FeedEntry {
+                    id: format!("urn:uuid:{}#{}", change.page_uuid, change.audit_id),
+                    title: format!("{} {}", change.page_name, verb),
+                    link: link.clone(),
+                    updated: change.changed_at,
+                    author: change.user_login,
+                    // the page name is already in the title
+                    summary: change
+                        .change_detail
+                        .filter(|detail| *detail != change.page_name),
+                }
+            })
+            .collect(),
+    };
+    Ok(feed.into_response())
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct RoleRequest {
+    pub role: Role,
+}
+
+pub async fn api_admin_users(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.reader;
+    let users = UserEntity::find_all(pool).await?;
+    let response: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_admin_grant_role(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(user_uuid): Path<Uuid>,
+    Json(request): Json<RoleRequest>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    UserEntity::set_role(pool, &identity, user_uuid, request.role).await?;
+    let user = UserEntity::find_by_uuid(pool, user_uuid).await?;
+    Ok(Json(UserResponse::from(user)).into_response())
+}
+
+pub async fn api_admin_revoke_role(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(user_uuid): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    UserEntity::revoke_role(pool, &identity, user_uuid).await?;
+    let user = UserEntity::find_by_uuid(pool, user_uuid).await?;
+    Ok(Json(UserResponse::from(user)).into_response())
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRequest {
+    pub group_name: String,
+}
+
+pub async fn api_admin_groups(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.reader;
+    let groups = GroupEntity::find_all(pool).await?;
+    let response: Vec<GroupResponse> = groups.into_iter().map(GroupResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_admin_create_group(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Json(request): Json<GroupRequest>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let group_name = request.group_name.trim().to_owned();
+    if group_name.is_empty() {
+        return Err(ServerError::BadRequest);
+    }
+    let pool = &state.writer;
+    let group = GroupEntity::create(pool, &identity, group_name).await?;
+    Ok(Json(GroupResponse::from(group)).into_response())
+}
+
+pub async fn api_admin_delete_group(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(group_uuid): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    GroupEntity::delete(pool, &identity, group_uuid).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn api_admin_add_group_member(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path((group_uuid, user_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    GroupEntity::add_member(pool, &identity, group_uuid, user_uuid).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn api_admin_remove_group_member(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path((group_uuid, user_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    GroupEntity::remove_member(pool, &identity, group_uuid, user_uuid).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Access entry targeting exactly one of a page or a name prefix
+/// and exactly one of a user or a group.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclRequest {
+    pub page_name: Option<String>,
+    pub name_prefix: Option<String>,
+    pub user_uuid: Option<Uuid>,
+    pub group_uuid: Option<Uuid>,
+    pub permission: Permission,
+}
+
+pub async fn api_admin_acls(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.reader;
+    let acls = AclEntity::find_all(pool).await?;
+    let response: Vec<AclResponse> = acls.into_iter().map(AclResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_admin_create_acl(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Json(request): Json<AclRequest>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let target = match (request.page_name, request.name_prefix) {
+        (Some(page_name), None) => AclTarget::Page(page_name),
+        (None, Some(prefix)) if !prefix.trim_end_matches('/').is_empty() => {
+            AclTarget::Prefix(prefix.trim_end_matches('/').to_owned())
+        }
+        _ => return Err(ServerError::BadRequest),
+    };
+    let principal = match (request.user_uuid, request.group_uuid) {
+        (Some(user_uuid), None) => AclPrincipal::User(user_uuid),
+        (None, Some(group_uuid)) => AclPrincipal::Group(group_uuid),
+        _ => return Err(ServerError::BadRequest),
+    };
+    let pool = &state.writer;
+    let acl_uuid = AclEntity::add(pool, &identity, target, principal, request.permission).await?;
+    Ok(Json(acl_uuid).into_response())
+}
+
+pub async fn api_admin_delete_acl(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Path(acl_uuid): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    AclEntity::delete(pool, &identity, acl_uuid).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn api_admin_reindex(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.writer;
+    let report = SearchEntity::reindex(pool, &identity, state.config.search.tokenizer).await?;
+    Ok(Json(report).into_response())
+}
+
+pub async fn api_admin_audit(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.reader;
+    let entries = AuditEntity::find_all(pool, query).await?;
+    let response: Vec<AuditResponse> = entries.into_iter().map(AuditResponse::from).collect();
+    Ok(Json(response).into_response())
+}
+
+pub async fn api_admin_webhook_deliveries(
+    Extension(state): Extension<Arc<ServerState>>,
+    Extension(identity): Extension<Identity>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> Result<Response, ServerError> {
+    identity.require(Role::Admin)?;
+    let pool = &state.reader;
+    let deliveries = WebhookDeliveryEntity::find_all(pool, query).await?;
+    let response: Vec<WebhookDeliveryResponse> = deliveries
+        .into_iter()
+        .map(WebhookDeliveryResponse::from)
+        .collect();
+    Ok(Json(response).into_response())
 }
 
 pub async fn home() -> impl IntoResponse {
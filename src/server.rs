@@ -1,12 +1,21 @@
-use crate::{config::Config, routes};
+use crate::{
+    auth,
+    config::Config,
+    csrf,
+    rate_limit::{self, RateLimiter},
+    routes,
+    webhook::Webhooks,
+};
 use anyhow::Result;
-use async_sqlite::Client;
+use async_sqlite::Pool;
 use axum::{
     Extension, Router,
     extract::DefaultBodyLimit,
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post, put},
 };
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::sync::Mutex;
@@ -15,30 +24,72 @@ use tokio::sync::Mutex;
 use axum::{
     body::Body,
     http::{HeaderValue, Request, header},
-    middleware::{self, Next},
+    middleware::Next,
     response::Response,
 };
 
+/// How long a pending OAuth state remains valid.
+const OAUTH_STATE_TTL: Duration = Duration::from_secs(600);
+
+/// Maximum number of pending OAuth states kept in memory.
+const OAUTH_STATE_CAPACITY: usize = 1024;
+
 #[derive(Clone, Debug)]
 pub struct ItemOauthAxum {
     pub verifier: String,
     pub created_at: SystemTime,
 }
 
+impl ItemOauthAxum {
+    /// Determine if this item has outlived the state TTL.
+    fn is_expired(&self) -> bool {
+        self.created_at
+            .elapsed()
+            .map(|elapsed| elapsed > OAUTH_STATE_TTL)
+            .unwrap_or(true)
+    }
+}
+
 #[derive(Clone)]
 pub struct ServerState {
-    pub client: Arc<Mutex<Client>>,
+    pub config: Config,
+    /// Connections shared by requests that only read.
+    pub reader: Pool,
+    /// Single connection for requests that write, so writers queue
+    /// rather than contend for the database lock.
+    pub writer: Pool,
     pub auth_db: Arc<Mutex<HashMap<String, ItemOauthAxum>>>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub webhooks: Webhooks,
 }
 
 impl ServerState {
-    pub async fn get(&self, key: String) -> Option<String> {
-        let db = self.auth_db.lock().await;
-        db.get(&key).map(|i| i.verifier.clone())
+    /// Remove the verifier for an OAuth state.
+    ///
+    /// States are single use so the entry is always removed;
+    /// expired entries yield `None`.
+    pub async fn take(&self, key: &str) -> Option<String> {
+        let mut db = self.auth_db.lock().await;
+        let item = db.remove(key)?;
+        if item.is_expired() {
+            None
+        } else {
+            Some(item.verifier)
+        }
     }
 
     pub async fn set(&self, key: String, value: String) {
         let mut db = self.auth_db.lock().await;
+        db.retain(|_, item| !item.is_expired());
+        if db.len() >= OAUTH_STATE_CAPACITY {
+            let oldest = db
+                .iter()
+                .min_by_key(|(_, item)| item.created_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                db.remove(&oldest);
+            }
+        }
         db.insert(
             key,
             ItemOauthAxum {
@@ -47,6 +98,17 @@ impl ServerState {
             },
         );
     }
+
+    /// Discard expired OAuth states.
+    pub async fn sweep(&self) {
+        let mut db = self.auth_db.lock().await;
+        let before = db.len();
+        db.retain(|_, item| !item.is_expired());
+        let removed = before - db.len();
+        if removed > 0 {
+            tracing::debug!(removed = %removed, "oauth::sweep");
+        }
+    }
 }
 
 /// Don't cache static assets in debug mode.
@@ -65,20 +127,54 @@ async fn set_static_cache_control(request: Request<Body>, next: Next) -> Respons
     response
 }
 
+/// State for handler tests, with the default configuration and both
+/// pools on the same database.
+#[cfg(test)]
+pub(crate) fn test_state(pool: Pool) -> Arc<ServerState> {
+    Arc::new(ServerState {
+        config: Config::default(),
+        reader: pool.clone(),
+        writer: pool,
+        auth_db: Arc::new(Mutex::new(HashMap::new())),
+        rate_limiter: Arc::default(),
+        webhooks: Webhooks::default(),
+    })
+}
+
 pub struct Server;
 
 impl Server {
     /// Start the server.
-    pub async fn start(config: Config, client: Client, open: bool) -> Result<()> {
+    pub async fn start(config: Config, reader: Pool, writer: Pool, open: bool) -> Result<()> {
+        let bind = config.bind;
+        let webhooks = Webhooks::start(config.webhooks.clone(), writer.clone());
         let state = Arc::new(ServerState {
-            client: Arc::new(Mutex::new(client)),
+            config,
+            reader,
+            writer,
             auth_db: Arc::new(Mutex::new(HashMap::new())),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            webhooks,
         });
 
-        tracing::info!(bind = %config.bind);
+        tracing::info!(bind = %bind);
+
+        let sweep_state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(OAUTH_STATE_TTL);
+            loop {
+                interval.tick().await;
+                sweep_state.sweep().await;
+                sweep_state
+                    .rate_limiter
+                    .sweep(&sweep_state.config.rate_limit)
+                    .await;
+            }
+        });
 
         let mut app = Router::new()
             .route("/login/github", get(github::login))
+            .route("/logout", get(auth::logout))
             .route("/files/{file_uuid}", get(routes::api_file_content))
             .route("/api/search", get(routes::api_search))
             .route("/api/page", post(routes::api_insert_page))
@@ -89,6 +185,77 @@ impl Server {
                     .delete(routes::api_delete_page),
             )
             .route("/api/page/recent", get(routes::api_recent_pages))
+            .route("/api/pages", get(routes::api_pages))
+            .route("/api/pages/suggest", get(routes::api_suggest_pages))
+            .route(
+                "/api/page/{page_name}/contributors",
+                get(routes::api_page_contributors),
+            )
+            .route(
+                "/api/page/{page_name}/children",
+                get(routes::api_page_children),
+            )
+            .route("/api/page/{page_name}/feed", get(routes::api_page_feed))
+            .route("/api/tags", get(routes::api_tags))
+            .route("/api/tags/{tag_name}", get(routes::api_tag_pages))
+            .route("/api/user", get(routes::api_current_user))
+            .route(
+                "/api/watches",
+                get(routes::api_watches).post(routes::api_create_watch),
+            )
+            .route("/api/watches/{page_name}", delete(routes::api_delete_watch))
+            .route("/api/changes/watched", get(routes::api_watched_changes))
+            .route(
+                "/api/tokens",
+                get(routes::api_tokens).post(routes::api_create_token),
+            )
+            .route("/api/tokens/{token_uuid}", delete(routes::api_delete_token))
+            .route(
+                "/api/searches",
+                get(routes::api_saved_searches).post(routes::api_create_saved_search),
+            )
+            .route(
+                "/api/searches/{search_name}",
+                get(routes::api_run_saved_search).delete(routes::api_delete_saved_search),
+            )
+            .route(
+                "/api/searches/{search_name}/feed",
+                get(routes::api_saved_search_feed),
+            )
+            .route("/api/admin/users", get(routes::api_admin_users))
+            .route("/api/admin/audit", get(routes::api_admin_audit))
+            .route(
+                "/api/admin/webhooks/deliveries",
+                get(routes::api_admin_webhook_deliveries),
+            )
+            .route("/api/admin/search/reindex", post(routes::api_admin_reindex))
+            .route(
+                "/api/admin/users/{user_uuid}/role",
+                put(routes::api_admin_grant_role).delete(routes::api_admin_revoke_role),
+            )
+            .route(
+                "/api/admin/groups",
+                get(routes::api_admin_groups).post(routes::api_admin_create_group),
+            )
+            .route(
+                "/api/admin/groups/{group_uuid}",
+                delete(routes::api_admin_delete_group),
+            )
+            .route(
+                "/api/admin/groups/{group_uuid}/members/{user_uuid}",
+                put(routes::api_admin_add_group_member)
+                    .delete(routes::api_admin_remove_group_member),
+            )
+            .route(
+                "/api/admin/acls",
+                get(routes::api_admin_acls).post(routes::api_admin_create_acl),
+            )
+            .route(
+                "/api/admin/acls/{acl_uuid}",
+                delete(routes::api_admin_delete_acl),
+            )
+            .route("/feeds/recent.atom", get(routes::recent_atom_feed))
+            .route("/feeds/recent.rss", get(routes::recent_rss_feed))
             .route("/api/github/callback", get(github::callback))
             .route("/", get(routes::home));
 
@@ -161,10 +328,14 @@ impl Server {
         );
 
         app = app
+            .layer(middleware::from_fn(rate_limit::limit_user))
+            .layer(middleware::from_fn(auth::authenticate))
+            .layer(middleware::from_fn(rate_limit::limit_ip))
+            .layer(middleware::from_fn(csrf::protect))
             .layer(Extension(state.clone()))
             .layer(DefaultBodyLimit::max(50 * 1024 * 1024));
 
-        let listener = tokio::net::TcpListener::bind(config.bind).await?;
+        let listener = tokio::net::TcpListener::bind(bind).await?;
         tracing::info!("listening on {}", listener.local_addr().unwrap());
         if open {
             tokio::spawn(async move {
@@ -172,13 +343,23 @@ impl Server {
                 open::that("http://localhost:8776").expect("to open URL");
             });
         }
-        axum::serve(listener, app).await?;
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
 
         Ok(())
     }
 }
 
 mod github {
+    use crate::auth::{Identity, session_cookie};
+    use crate::entity::{
+        audit::Actor,
+        session::SessionEntity,
+        user::{GithubUser, UserEntity},
+    };
     use crate::error::ServerError;
 
     use super::ServerState;
@@ -186,6 +367,7 @@ mod github {
     use axum::Extension;
     use axum::extract::Query;
     use axum::response::{IntoResponse, Redirect, Response};
+    use axum_extra::extract::cookie::CookieJar;
     use oauth_axum::providers::github::GithubProvider;
     use oauth_axum::{CustomProvider, OAuthClient};
     use std::sync::Arc;
@@ -227,17 +409,41 @@ mod github {
             .ok_or(ServerError::NoGeneratedOauthUrl)
     }
 
+    /// Fetch the profile of the user that authorized the token.
+    async fn fetch_user(token: &str) -> Result<GithubUser, ServerError> {
+        let profile = reqwest::Client::new()
+            .get("https://api.github.com/user")
+            .bearer_auth(token)
+            .header(reqwest::header::USER_AGENT, "twilite")
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<GithubUser>()
+            .await?;
+        Ok(profile)
+    }
+
     pub async fn callback(
         Extension(state): Extension<Arc<ServerState>>,
+        Extension(identity): Extension<Identity>,
+        jar: CookieJar,
         Query(queries): Query<OauthCallback>,
     ) -> Result<Response, ServerError> {
         if let (Some(oauth_code), Some(oauth_state)) = (queries.code, queries.state) {
-            let item = state.get(oauth_state.clone()).await;
-            let token = get_client()
-                .generate_token(oauth_code, item.unwrap())
-                .await?;
-            println!("Authorized...{}", token);
-            Ok(Redirect::temporary("/").into_response())
+            let verifier = state
+                .take(&oauth_state)
+                .await
+                .ok_or(ServerError::InvalidOauthState)?;
+            let token = get_client().generate_token(oauth_code, verifier).await?;
+            let profile = fetch_user(&token).await?;
+            let client_ip = identity.client_ip;
+            let pool = &state.writer;
+            let user = UserEntity::upsert_github(pool, profile, &state.config.auth).await?;
+            let session = SessionEntity::create(pool, Actor::new(&user, client_ip), &user).await?;
+            tracing::info!(login = %user.user_login, "auth::login");
+            let cookie = session_cookie(session, state.config.auth.secure_cookie);
+            Ok((jar.add(cookie), Redirect::temporary("/")).into_response())
         } else {
             Ok(Redirect::temporary("/").into_response())
         }
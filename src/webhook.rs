@@ -0,0 +1,336 @@
+//! Outgoing webhooks.
+//!
+//! Handlers emit events onto a queue and a background task logs each
+//! payload in `webhook_deliveries` before posting it to the webhooks
+//! subscribed to the event, retrying failed attempts with backoff.
+//! Deliveries still pending when the server stopped are resumed from
+//! the log when it starts again.
+use crate::{
+    auth::Identity,
+    config::Webhook,
+    entity::{acl::Access, webhook_delivery::WebhookDeliveryEntity},
+    error::ServerError,
+};
+use async_sqlite::Pool;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sql_query_builder as sql;
+use std::time::Duration;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Events waiting to be delivered before new ones are dropped.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Attempts per delivery, the delay doubles after each failure.
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event a webhook can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    PageCreated,
+    PageUpdated,
+    PageRenamed,
+    PageDeleted,
+    FileUploaded,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PageCreated => "page.created",
+            Self::PageUpdated => "page.updated",
+            Self::PageRenamed => "page.renamed",
+            Self::PageDeleted => "page.deleted",
+            Self::FileUploaded => "file.uploaded",
+        }
+    }
+}
+
+/// Body of a webhook request.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub event: &'static str,
+    pub occurred_at: String,
+    /// Login of the user that caused the event, if signed in.
+    pub user_login: Option<String>,
+    pub page_uuid: Uuid,
+    pub page_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+}
+
+impl WebhookPayload {
+    pub fn new(
+        event: WebhookEvent,
+        identity: &Identity,
+        page_uuid: Uuid,
+        page_name: String,
+    ) -> Self {
+        Self {
+            event: event.as_str(),
+            occurred_at: UtcDateTime::now().format(&Rfc3339).unwrap_or_default(),
+            user_login: identity.user.as_ref().map(|user| user.user_login.clone()),
+            page_uuid,
+            page_name,
+            previous_name: None,
+            file_name: None,
+        }
+    }
+}
+
+/// Queue of events for the configured webhooks, events are discarded
+/// when there are none.
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    sender: Option<mpsc::Sender<WebhookPayload>>,
+}
+
+impl Webhooks {
+    /// Start delivering events to the webhooks.
+    pub fn start(webhooks: Vec<Webhook>, pool: Pool) -> Self {
+        if webhooks.is_empty() {
+            return Self::default();
+        }
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run(webhooks, pool, receiver));
+        Self {
+            sender: Some(sender),
+        }
+    }
+
+    /// Queue an event without waiting, so a slow endpoint never holds
+    /// up a request.
+    pub fn emit(&self, payload: WebhookPayload) {
+        if let Some(sender) = &self.sender
+            && let Err(e) = sender.try_send(payload)
+        {
+            tracing::warn!(error = %e, "webhook::emit");
+        }
+    }
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of the timestamp, a dot
+/// and the body, so a captured request can't be replayed later with
+/// a fresh timestamp.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// Whether anyone may read the pages an event names, events of pages
+/// restricted by access entries are not sent outside the wiki.
+async fn is_public(pool: &Pool, payload: &WebhookPayload) -> Result<bool, ServerError> {
+    let condition = Access::default()
+        .read_condition("p")
+        .unwrap_or_else(|| "1".to_owned());
+    let query = sql::Select::new()
+        .select("COUNT(*)")
+        .from(
+            "(SELECT (SELECT page_id FROM pages WHERE page_uuid = ?1) AS page_id, ?2 AS page_name \
+            UNION ALL SELECT NULL, ?3 WHERE ?3 IS NOT NULL) p",
+        )
+        .where_clause(&format!("NOT {}", condition));
+
+    let params = (
+        payload.page_uuid.to_string(),
+        payload.page_name.clone(),
+        payload.previous_name.clone(),
+    );
+    let restricted: i64 = pool
+        .conn(move |conn| {
+            conn.prepare_cached(&query.as_string())?
+                .query_row(params, |row| row.get(0))
+        })
+        .await?;
+    Ok(restricted == 0)
+}
+
+/// Logged payload still to be delivered.
+struct Delivery {
+    delivery_id: i64,
+    delivery_uuid: Uuid,
+    event: String,
+    body: String,
+    attempt_count: u32,
+}
+
+async fn run(webhooks: Vec<Webhook>, pool: Pool, mut receiver: mpsc::Receiver<WebhookPayload>) {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    resume(&client, &pool, &webhooks).await;
+    while let Some(payload) = receiver.recv().await {
+        match is_public(&pool, &payload).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::error!(error = ?e, "webhook::run");
+                continue;
+            }
+        }
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!(error = ?e, "webhook::run");
+                continue;
+            }
+        };
+        let subscribed = webhooks.iter().filter(|webhook| {
+            webhook.events.is_empty() || webhook.events.iter().any(|e| e == payload.event)
+        });
+        for webhook in subscribed {
+            tokio::spawn(deliver(
+                client.clone(),
+                pool.clone(),
+                webhook.clone(),
+                payload.event,
+                body.clone(),
+            ));
+        }
+    }
+}
+
+/// Deliver the payloads left pending by a previous run to the webhooks
+/// that are still configured.
+async fn resume(client: &reqwest::Client, pool: &Pool, webhooks: &[Webhook]) {
+    let pending = match WebhookDeliveryEntity::find_pending(pool, MAX_ATTEMPTS).await {
+        Ok(pending) => pending,
+        Err(e) => {
+            tracing::error!(error = ?e, "webhook::resume");
+            return;
+        }
+    };
+    for delivery in pending {
+        let Some(webhook) = webhooks
+            .iter()
+            .find(|webhook| webhook.url == delivery.webhook_url)
+        else {
+            continue;
+        };
+        tokio::spawn(attempt(
+            client.clone(),
+            pool.clone(),
+            webhook.clone(),
+            Delivery {
+                delivery_id: delivery.delivery_id,
+                delivery_uuid: delivery.delivery_uuid,
+                event: delivery.event_name,
+                body: delivery.payload,
+                attempt_count: delivery.attempt_count.try_into().unwrap_or(MAX_ATTEMPTS),
+            },
+        ));
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    pool: Pool,
+    webhook: Webhook,
+    event: &'static str,
+    body: String,
+) {
+    let delivery_uuid = Uuid::new_v4();
+    let delivery_id = match WebhookDeliveryEntity::create(
+        &pool,
+        delivery_uuid,
+        webhook.url.clone(),
+        event.to_owned(),
+        body.clone(),
+    )
+    .await
+    {
+        Ok(delivery_id) => delivery_id,
+        Err(e) => {
+            tracing::error!(error = ?e, url = %webhook.url, "webhook::deliver");
+            return;
+        }
+    };
+    let delivery = Delivery {
+        delivery_id,
+        delivery_uuid,
+        event: event.to_owned(),
+        body,
+        attempt_count: 0,
+    };
+    attempt(client, pool, webhook, delivery).await;
+}
+
+/// Post a logged payload until it is delivered, fails for good or runs
+/// out of attempts.
+async fn attempt(client: reqwest::Client, pool: Pool, webhook: Webhook, delivery: Delivery) {
+    let Delivery {
+        delivery_id,
+        delivery_uuid,
+        event,
+        body,
+        attempt_count,
+    } = delivery;
+    let mut delay = RETRY_DELAY;
+    for attempt in attempt_count + 1..=MAX_ATTEMPTS {
+        let timestamp = UtcDateTime::now().unix_timestamp();
+        let result = client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::USER_AGENT, "twilite")
+            .header("X-Twilite-Event", &event)
+            .header("X-Twilite-Delivery", delivery_uuid.to_string())
+            .header("X-Twilite-Timestamp", timestamp.to_string())
+            .header(
+                "X-Twilite-Signature",
+                sign(&webhook.secret, timestamp, body.as_bytes()),
+            )
+            .body(body.clone())
+            .send()
+            .await;
+        let (status_code, error) = match result {
+            Ok(response) => (Some(response.status().as_u16()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let delivered = status_code.is_some_and(|status| (200..300).contains(&status));
+        if let Err(e) =
+            WebhookDeliveryEntity::record_attempt(&pool, delivery_id, status_code, error, delivered)
+                .await
+        {
+            tracing::error!(error = ?e, url = %webhook.url, "webhook::deliver");
+        }
+        if delivered {
+            return;
+        }
+        // other client errors will fail again the same way
+        let retry =
+            status_code.is_none_or(|status| status >= 500 || status == 408 || status == 429);
+        tracing::warn!(url = %webhook.url, attempt = %attempt, status = ?status_code, "webhook::deliver");
+        if !retry || attempt == MAX_ATTEMPTS {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::sign;
+
+    #[test]
+    fn signs_payloads() {
+        // RFC 4231 test case 2 with a timestamp in front of the body
+        assert_eq!(
+            sign("Jefe", 1700000000, b"what do ya want for nothing?"),
+            "sha256=1cdd0650c8be1cb0974b1788d458b1e781206cfef59b85faafc582d2e182c57e"
+        );
+    }
+}